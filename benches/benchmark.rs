@@ -6,8 +6,9 @@ const SAMPLE: usize = 10;
 
 const TRANSACTIONS: usize = 10_000_000;
 const CLIENTS: usize = 10;
+const MANY_CLIENTS: usize = u16::MAX as usize;
 const MAX_AMOUNT: f32 = 100.0;
-const TX_TYPES: [&'static str; 5] = ["deposit", "withdrawal", "dispute", "resolve", "chargeback"];
+const TX_TYPES: [&str; 5] = ["deposit", "withdrawal", "dispute", "resolve", "chargeback"];
 
 #[derive(Default)]
 struct Sink;
@@ -26,22 +27,22 @@ struct CsvInput {
     current_line: usize,
     buffer: Vec<u8>,
     tx_id: usize,
+    clients: usize,
 
     _rng: rand::rngs::ThreadRng,
 }
 
-impl Default for CsvInput {
-    fn default() -> Self {
+impl CsvInput {
+    fn new(clients: usize) -> Self {
         CsvInput {
             current_line: TRANSACTIONS,
             buffer: Vec::new(),
             tx_id: 0usize,
+            clients,
             _rng: rand::thread_rng(),
         }
     }
-}
 
-impl CsvInput {
     fn header() -> &'static str {
         "type,client,tx,amount\n"
     }
@@ -54,7 +55,7 @@ impl CsvInput {
         format!(
             "{transaction_type},{client_id},{transaction_id},{amount}\n",
             transaction_type = transaction_type,
-            client_id = self._rng.gen_range(1..CLIENTS + 1),
+            client_id = self._rng.gen_range(1..self.clients + 1),
             transaction_id = match transaction_type {
                 "deposit" | "withdrawal" => {
                     self.tx_id += 1;
@@ -64,7 +65,7 @@ impl CsvInput {
                 _ => 1,
             },
             amount = match transaction_type {
-                "deposit" | "withdrawal" => self._rng.gen_range(0.0..MAX_AMOUNT).to_string(),
+                "deposit" | "withdrawal" => format!("{:.4}", self._rng.gen_range(0.0..MAX_AMOUNT)),
                 _ => "".to_string(),
             }
         )
@@ -98,7 +99,7 @@ impl std::io::Read for CsvInput {
             }
             _ => {
                 buf.copy_from_slice(&self.buffer[..buf.len()]);
-                self.buffer = (&self.buffer[buf.len()..]).to_vec();
+                self.buffer = self.buffer[buf.len()..].to_vec();
 
                 Ok(buf.len())
             }
@@ -107,16 +108,21 @@ impl std::io::Read for CsvInput {
 }
 
 pub fn krct_benchmark(c: &mut Criterion) {
-    c.bench_function(&format!("Bench with {} lines", TRANSACTIONS), |bencher| {
-        bencher.iter(|| {
-            let input = CsvInput::default();
-            let output = Sink::default();
-            krct::Krct::read(input)
-                .expect("Error occurred meanwhile benching Krct")
-                .dump(output)
-                .expect("Error occurred meanwhile benching Krct");
-        })
-    });
+    for clients in [CLIENTS, MANY_CLIENTS] {
+        c.bench_function(
+            &format!("Bench with {} lines and {} clients", TRANSACTIONS, clients),
+            |bencher| {
+                bencher.iter(|| {
+                    let input = CsvInput::new(clients);
+                    let output = Sink;
+                    krct::Krct::read(input)
+                        .expect("Error occurred meanwhile benching Krct")
+                        .dump(output)
+                        .expect("Error occurred meanwhile benching Krct");
+                })
+            },
+        );
+    }
 }
 
 criterion_group! {
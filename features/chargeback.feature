@@ -11,8 +11,8 @@ Feature: A simple toy payments engine chargeback a disputed transaction
     When the engine is executed
     Then the following output should be generated
     """
-    client,available,held,total,locked
-    1,1.0,0.0,1.0,true
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.0,0.0,1.0,true,chargeback,0.0,0.0
     """
 
   Scenario: Cannot withdraw from a locked account
@@ -28,8 +28,8 @@ Feature: A simple toy payments engine chargeback a disputed transaction
     When the engine is executed
     Then the following output should be generated
     """
-    client,available,held,total,locked
-    1,1.0,0.0,1.0,true
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.0,0.0,1.0,true,chargeback,0.0,0.0
     """
 
   Scenario: Cannot deposit to a locked account
@@ -45,6 +45,6 @@ Feature: A simple toy payments engine chargeback a disputed transaction
     When the engine is executed
     Then the following output should be generated
     """
-    client,available,held,total,locked
-    1,1.0,0.0,1.0,true
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.0,0.0,1.0,true,chargeback,0.0,0.0
     """
@@ -0,0 +1,112 @@
+Feature: A simple toy payments engine command line interface
+  Scenario: Successful run exits with zero
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    1.0
+    """
+    When the binary is executed with "--sorted {input}"
+    Then the exit code should be 0
+    And the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.0,0.0,1.0,false,,0.0,0.0
+    """
+
+  Scenario: Missing input is reported as JSON
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    """
+    When the binary is executed with "--error-format json missing.csv"
+    Then the exit code should be 2
+    And the following error should be printed
+    """
+    {"code":2,"error":"input","message":"Input missing.csv could not be opened: No such file or directory (os error 2)"}
+    """
+
+  Scenario: Malformed row aborts the processing
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    1.0
+    deposit,      x,        2,    1.0
+    """
+    When the binary is executed with "--strict abort --error-format json {input}"
+    Then the exit code should be 3
+    And the following error should be printed
+    """
+    {"code":3,"error":"parse","input":0,"line":3,"message":"Malformed row at line 3: deposit,x,2,1.0: CSV deserialize error: record 2 (line: 3, byte: 71): field 1: invalid digit found in string"}
+    """
+
+  Scenario: Unwritable output is a dump failure
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    1.0
+    """
+    When the binary is executed with "--output missing/output.csv {input}"
+    Then the exit code should be 4
+    And the following error should be printed
+    """
+    Dump error: No such file or directory (os error 2)
+    """
+
+  Scenario: Input is read from stdin
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    1.0
+    deposit,      2,        2,    2.0
+    """
+    When the binary is executed with "--sorted -" reading the CSV file from stdin
+    Then the exit code should be 0
+    And the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.0,0.0,1.0,false,,0.0,0.0
+    2,2.0,0.0,2.0,false,,0.0,0.0
+    """
+
+  Scenario: Files of a directory are read in sorted order
+    Given the following CSV file "b.csv" in the input directory
+    """
+    type,         client,   tx,   amount
+    withdrawal,   1,        2,    1.0
+    """
+    And the following CSV file "a.csv" in the input directory
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    1.5
+    """
+    And the following CSV file "c.csv" in the input directory
+    """
+    type,         client,   tx,   amount
+    dispute,      1,        1,
+    """
+    When the binary is executed with "--sorted {directory}"
+    Then the exit code should be 0
+    And the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,-1.0,1.5,0.5,false,,0.0,0.0
+    """
+
+  Scenario: Stdin is read before a directory in the order of the arguments
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    2.0
+    """
+    And the following CSV file "a.csv" in the input directory
+    """
+    type,         client,   tx,   amount
+    withdrawal,   1,        2,    1.5
+    """
+    When the binary is executed with "--sorted - {directory}" reading the CSV file from stdin
+    Then the exit code should be 0
+    And the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,0.5,0.0,0.5,false,,0.0,0.0
+    """
@@ -12,9 +12,9 @@ Feature: A simple toy payments engine deposits and withdraws
     When the engine is executed
     Then the following output should be generated
     """
-    client,available,held,total,locked
-    1,1.5,0.0,1.5,false
-    2,2.0,0.0,2.0,false
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.5,0.0,1.5,false,,0.0,0.0
+    2,2.0,0.0,2.0,false,,0.0,0.0
     """
 
   Scenario: Withdraws cannot go under zero
@@ -27,8 +27,8 @@ Feature: A simple toy payments engine deposits and withdraws
     When the engine is executed
     Then the following output should be generated
     """
-    client,available,held,total,locked
-    1,1.0,0.0,1.0,false
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.0,0.0,1.0,false,,0.0,0.0
     """
 
   Scenario: Withdraw the full amount
@@ -41,6 +41,82 @@ Feature: A simple toy payments engine deposits and withdraws
     When the engine is executed
     Then the following output should be generated
     """
-    client,available,held,total,locked
-    1,0.0,0.0,0.0,false
-    """
\ No newline at end of file
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,0.0,0.0,0.0,false,,0.0,0.0
+    """
+
+  Scenario: Amounts are summed up exactly
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    0.1
+    deposit,    1,        2,    0.2
+    deposit,    1,        3,    0.0001
+    withdrawal, 1,        4,    0.3
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,0.0001,0.0,0.0001,false,,0.0,0.0
+    """
+
+  Scenario: Deposit overflowing the balance is ignored
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    922337203685477.5807
+    deposit,    1,        2,    0.0001
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,922337203685477.5807,0.0,922337203685477.5807,false,,0.0,0.0
+    """
+
+  Scenario: Transaction ids cannot be reused
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    deposit,    2,        1,    2.0
+    deposit,    1,        2,    1.0
+    withdrawal, 1,        2,    0.5
+    """
+    When the engine is executed with csv rejection report
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,2.0,0.0,2.0,false,,0.0,0.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    2,1,deposit,duplicate_tx
+    1,2,withdrawal,duplicate_tx
+    """
+
+  Scenario: Deposits and withdraws of a non-positive amount are rejected
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    5.0
+    deposit,    1,        2,    -10
+    deposit,    1,        3,    0
+    withdrawal, 1,        4,    -5.0
+    withdrawal, 1,        5,    1.0
+    """
+    When the engine is executed with csv rejection report
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,4.0,0.0,4.0,false,,0.0,0.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    1,2,deposit,invalid_amount
+    1,3,deposit,invalid_amount
+    1,4,withdrawal,invalid_amount
+    """
@@ -0,0 +1,80 @@
+Feature: A simple toy payments engine dispute a withdrawal
+  Scenario: Dispute a withdrawal
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    3.0
+    withdrawal, 1,        2,    1.0
+    dispute,    1,        2,
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,2.0,1.0,3.0,false,,0.0,0.0
+    """
+
+  Scenario: Pending credit of a disputed withdrawal cannot be withdrawn
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    withdrawal, 1,        2,    1.0
+    dispute,    1,        2,
+    withdrawal, 1,        3,    0.5
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,0.0,1.0,1.0,false,,0.0,0.0
+    """
+
+  Scenario: Resolve a disputed withdrawal
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    3.0
+    withdrawal, 1,        2,    1.0
+    dispute,    1,        2,
+    resolve,    1,        2,
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,2.0,0.0,2.0,false,,0.0,0.0
+    """
+
+  Scenario: Chargeback a disputed withdrawal
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    3.0
+    withdrawal, 1,        2,    1.0
+    dispute,    1,        2,
+    chargeback, 1,        2,
+    withdrawal, 1,        3,    3.0
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,0.0,0.0,0.0,false,,0.0,0.0
+    """
+
+  Scenario: Cannot resolve a not disputed withdrawal
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    3.0
+    withdrawal, 1,        2,    1.0
+    resolve,    1,        2,
+    chargeback, 1,        2,
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,2.0,0.0,2.0,false,,0.0,0.0
+    """
@@ -10,8 +10,8 @@ Feature: A simple toy payments engine dispute a transaction
     When the engine is executed
     Then the following output should be generated
     """
-    client,available,held,total,locked
-    1,3.0,1.0,4.0,false
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,3.0,1.0,4.0,false,,0.0,0.0
     """
 
   Scenario: Disputes affect withdraws
@@ -25,6 +25,29 @@ Feature: A simple toy payments engine dispute a transaction
     When the engine is executed
     Then the following output should be generated
     """
-    client,available,held,total,locked
-    1,0.0,1.0,1.0,false
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,0.0,1.0,1.0,false,,0.0,0.0
+    """
+
+  Scenario: Cannot dispute a transaction of another client
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    deposit,    2,        2,    1.0
+    dispute,    2,        1,
+    dispute,    2,        3,
+    """
+    When the engine is executed with csv rejection report
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.0,0.0,1.0,false,,0.0,0.0
+    2,1.0,0.0,1.0,false,,0.0,0.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    2,1,dispute,foreign_tx
+    2,3,dispute,unknown_tx
     """
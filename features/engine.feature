@@ -10,6 +10,23 @@ Feature: A simple toy payments engine
     When the engine is executed
     Then the following output should be generated
     """
-    client,available,held,total,locked
-    1,2.5,0.0,2.5,false
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,2.5,0.0,2.5,false,,0.0,0.0
+    """
+
+  Scenario: Records are submitted one by one
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    2.0
+    deposit,      2,        2,    1.0
+    withdrawal,   1,        3,    0.5
+    dispute,      2,        2,
+    """
+    When the engine is fed record by record
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.5,0.0,1.5,false,,0.0,0.0
+    2,0.0,1.0,1.0,false,,0.0,0.0
     """
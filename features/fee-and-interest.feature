@@ -0,0 +1,84 @@
+Feature: A simple toy payments engine posts fees and interest
+  Scenario: Fees and interest are posted and totaled separately
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    10.0
+    fee,        1,        2,    1.5
+    interest,   1,        3,    0.25
+    fee,        1,        4,    0.5
+    interest,   2,        5,    1.0
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,8.25,0.0,8.25,false,,2.0,0.25
+    2,1.0,0.0,1.0,false,,0.0,1.0
+    """
+
+  Scenario: Fees and interest are not disputable
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    10.0
+    fee,        1,        2,    1.0
+    interest,   1,        3,    1.0
+    dispute,    1,        2,
+    dispute,    1,        3,
+    """
+    When the engine is executed with csv rejection report
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,10.0,0.0,10.0,false,,1.0,1.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    1,2,dispute,unknown_tx
+    1,3,dispute,unknown_tx
+    """
+
+  Scenario: Fees and interest of a non-positive amount are rejected
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    3,        1,    10.0
+    fee,        3,        4,    -50
+    interest,   3,        5,    -5.0
+    fee,        3,        6,    0
+    """
+    When the engine is executed with csv rejection report
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    3,10.0,0.0,10.0,false,,0.0,0.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    3,4,fee,invalid_amount
+    3,5,interest,invalid_amount
+    3,6,fee,invalid_amount
+    """
+
+  Scenario Outline: A fee exceeding the available amount is treated according to the policy
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    fee,        1,        2,    1.5
+    """
+    When the engine is executed with <policy> fee policy
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    <output>
+    """
+
+    Examples:
+      | policy | output                                |
+      | allow  | 1,-0.5,0.0,-0.5,false,,1.5,0.0        |
+      | cap    | 1,0.0,0.0,0.0,false,,1.0,0.0          |
+      | forbid | 1,1.0,0.0,1.0,false,,0.0,0.0          |
@@ -0,0 +1,89 @@
+Feature: A simple toy payments engine recovering from its journal
+  Scenario: A run died halfway is recovered without applying an event twice
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    2.0
+    deposit,      2,        2,    1.0
+    withdrawal,   1,        3,    0.5
+    """
+    When the binary is executed with "--journal {journal} --checkpoint-interval 2 {input}"
+    Then the exit code should be 0
+    Given the following CSV file replacing the previous one
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    2.0
+    deposit,      2,        2,    1.0
+    withdrawal,   1,        3,    0.5
+    dispute,      1,        1,
+    deposit,      3,        4,    1.0
+    """
+    When the binary is executed with "--journal {journal} --recover --sorted {input}"
+    Then the exit code should be 0
+    And the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,-0.5,2.0,1.5,false,,0.0,0.0
+    2,1.0,0.0,1.0,false,,0.0,0.0
+    3,1.0,0.0,1.0,false,,0.0,0.0
+    """
+
+  Scenario: Recovering from a journal continues the starting state
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    2.0
+    """
+    When the binary is executed with "--state-out {state} {input}"
+    Then the exit code should be 0
+    Given the following CSV file replacing the previous one
+    """
+    type,         client,   tx,   amount
+    withdrawal,   1,        2,    0.5
+    """
+    When the binary is executed with "--state-in {state} --journal {journal} {input}"
+    Then the exit code should be 0
+    When the binary is executed with "--journal {journal} --recover {input}"
+    Then the exit code should be 0
+    And the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.5,0.0,1.5,false,,0.0,0.0
+    """
+
+  Scenario: Recovering needs a journal
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    """
+    When the binary is executed with "--recover {input}"
+    Then the exit code should be 1
+
+  Scenario: Rejection report of a recovered run is continued
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    withdrawal,   1,        1,    1.0
+    deposit,      1,        2,    2.0
+    deposit,      1,        3,    2.0
+    deposit,      1,        4,    2.0
+    """
+    When the binary is executed with "--journal {journal} --checkpoint-interval 1 --rejections {state} {input}"
+    Then the exit code should be 0
+    Given the following CSV file replacing the previous one
+    """
+    type,         client,   tx,   amount
+    withdrawal,   1,        1,    1.0
+    deposit,      1,        2,    2.0
+    deposit,      1,        3,    2.0
+    deposit,      1,        4,    2.0
+    withdrawal,   2,        5,    1.0
+    """
+    When the binary is executed with "--journal {journal} --recover --rejections {state} {input}"
+    Then the exit code should be 0
+    And the following should be written to the state file
+    """
+    client,tx,type,reason
+    1,1,withdrawal,insufficient_funds
+    2,5,withdrawal,insufficient_funds
+    """
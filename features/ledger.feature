@@ -0,0 +1,84 @@
+Feature: A simple toy payments engine writes the ledger of every client
+  Scenario: Every applied event is listed with the resulting balances
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount,   destination
+    deposit,      1,        1,    5.0,
+    deposit,      2,        2,    2.0,
+    withdrawal,   1,        3,    9.0,
+    dispute,      2,        2,    0.5,
+    transfer,     1,        4,    1.0,      2
+    resolve,      2,        2,    ,
+    dispute,      1,        1,    ,
+    chargeback,   1,        1,    ,
+    fee,          2,        5,    0.1,
+    """
+    When the engine is executed with csv ledger
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,-1.0,0.0,-1.0,true,chargeback,0.0,0.0
+    2,2.9,0.0,2.9,false,,0.1,0.0
+    """
+    And the following ledger should be written
+    """
+    client,tx,type,direction,counterparty,amount,available,held,total,state
+    1,1,deposit,,,5.0,5.0,0.0,5.0,charged_back
+    1,4,transfer,outgoing,2,1.0,4.0,0.0,4.0,recorded
+    1,1,dispute,,,5.0,-1.0,5.0,4.0,charged_back
+    1,1,chargeback,,,5.0,-1.0,0.0,-1.0,charged_back
+    2,2,deposit,,,2.0,2.0,0.0,2.0,recorded
+    2,2,dispute,,,0.5,1.5,0.5,2.0,recorded
+    2,4,transfer,incoming,1,1.0,2.5,0.5,3.0,recorded
+    2,2,resolve,,,0.5,3.0,0.0,3.0,recorded
+    2,5,fee,,,0.1,2.9,0.0,2.9,
+    """
+
+  Scenario: The ledger is written as NDJSON
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    1.0
+    """
+    When the engine is executed with ndjson ledger
+    Then the following ledger should be written
+    """
+    {"client":1,"tx":1,"type":"deposit","direction":null,"counterparty":null,"amount":"1.0","available":"1.0","held":"0.0","total":"1.0","state":"recorded"}
+    """
+
+  Scenario: The ledger is written by the binary
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    1.0
+    withdrawal,   1,        2,    0.25
+    """
+    When the binary is executed with "--ledger {state} --ledger-format csv {input}"
+    Then the exit code should be 0
+    And the following should be written to the state file
+    """
+    client,tx,type,direction,counterparty,amount,available,held,total,state
+    1,1,deposit,,,1.0,1.0,0.0,1.0,recorded
+    1,2,withdrawal,,,0.25,0.75,0.0,0.75,recorded
+    """
+
+  Scenario: Ledger of a restored state starts from the restored balances
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    1.0
+    """
+    When the binary is executed with "--state-out {state} {input}"
+    Then the exit code should be 0
+    Given the following CSV file replacing the previous one
+    """
+    type,         client,   tx,   amount
+    withdrawal,   1,        2,    0.25
+    """
+    When the binary is executed with "--state-in {state} --ledger {state} {input}"
+    Then the exit code should be 0
+    And the following should be written to the state file
+    """
+    client,tx,type,direction,counterparty,amount,available,held,total,state
+    1,2,withdrawal,,,0.25,0.75,0.0,0.75,recorded
+    """
@@ -0,0 +1,72 @@
+Feature: A simple toy payments engine lock and unlock an account
+  Scenario: Lock an account manually
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    lock,       1,        2,
+    deposit,    1,        3,    1.0
+    withdrawal, 1,        4,    0.5
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.0,0.0,1.0,true,manual,0.0,0.0
+    """
+
+  Scenario: Unlock a manually locked account
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    lock,       1,        2,
+    unlock,     1,        3,
+    withdrawal, 1,        4,    0.5
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,0.5,0.0,0.5,false,,0.0,0.0
+    """
+
+  Scenario: Unlock an account locked by a chargeback
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    deposit,    1,        2,    2.0
+    dispute,    1,        1,
+    chargeback, 1,        1,
+    unlock,     1,        3,
+    withdrawal, 1,        4,    0.5
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.5,0.0,1.5,false,,0.0,0.0
+    """
+
+  Scenario: Cannot unlock an account, which is not locked
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    unlock,     1,        2,
+    lock,       1,        3,
+    lock,       1,        4,
+    """
+    When the engine is executed with csv rejection report
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.0,0.0,1.0,true,manual,0.0,0.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    1,2,unlock,invalid_state
+    1,4,lock,account_locked
+    """
@@ -0,0 +1,65 @@
+Feature: A simple toy payments engine applies the locked-account policy
+  Scenario: Locked account accepts deposits, but not withdrawals
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    2.0
+    lock,       1,        2,
+    deposit,    1,        3,    1.0
+    withdrawal, 1,        4,    0.5
+    """
+    When the engine is executed with locked accounts accepting deposit
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,3.0,0.0,3.0,true,manual,0.0,0.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    1,4,withdrawal,account_locked
+    """
+
+  Scenario: Open disputes are settled on a locked account
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    deposit,    1,        2,    2.0
+    dispute,    1,        1,
+    dispute,    1,        2,
+    chargeback, 1,        1,
+    resolve,    1,        2,
+    deposit,    1,        3,    1.0
+    """
+    When the engine is executed with locked accounts accepting dispute,resolve,chargeback
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,2.0,0.0,2.0,true,chargeback,0.0,0.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    1,3,deposit,account_locked
+    """
+
+  Scenario: Nothing is accepted by default
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    lock,       1,        2,
+    deposit,    1,        3,    1.0
+    """
+    When the engine is executed with locked accounts accepting none
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.0,0.0,1.0,true,manual,0.0,0.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    1,3,deposit,account_locked
+    """
@@ -0,0 +1,71 @@
+Feature: A simple toy payments engine dispute an already spent deposit
+  Scenario: Available amount goes negative by default
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    withdrawal, 1,        2,    0.75
+    dispute,    1,        1,
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,-0.75,1.0,0.25,false,,0.0,0.0
+    """
+
+  Scenario: Dispute is rejected if negative balance is forbidden
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    withdrawal, 1,        2,    0.75
+    dispute,    1,        1,
+    """
+    When the engine is executed with forbid negative balance policy
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,0.25,0.0,0.25,false,,0.0,0.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    1,1,dispute,insufficient_funds
+    """
+
+  Scenario: Only the available amount is held if it is capped
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    withdrawal, 1,        2,    0.75
+    dispute,    1,        1,
+    chargeback, 1,        1,
+    """
+    When the engine is executed with cap negative balance policy
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,0.0,0.0,0.0,true,chargeback,0.0,0.0
+    """
+
+  Scenario: Dispute is rejected if nothing is available to cap
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    withdrawal, 1,        2,    1.0
+    dispute,    1,        1,
+    """
+    When the engine is executed with cap negative balance policy
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,0.0,0.0,0.0,false,,0.0,0.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    1,1,dispute,insufficient_funds
+    """
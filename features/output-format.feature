@@ -0,0 +1,45 @@
+Feature: A simple toy payments engine output formats
+  Scenario: Clients are written as a JSON array
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    2.0
+    deposit,      2,        2,    1.0
+    dispute,      2,        2,
+    """
+    When the engine is executed with json output
+    Then the following output should be generated
+    """
+    [{"client":1,"available":"2.0","held":"0.0","total":"2.0","locked":false,"lock_reason":null,"fees":"0.0","interest":"0.0"},{"client":2,"available":"0.0","held":"1.0","total":"1.0","locked":false,"lock_reason":null,"fees":"0.0","interest":"0.0"}]
+    """
+
+  Scenario: Clients are written as newline delimited JSON
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    2.0
+    lock,         1,        2,
+    """
+    When the engine is executed with ndjson output
+    Then the following output should be generated
+    """
+    {"client":1,"available":"2.0","held":"0.0","total":"2.0","locked":true,"lock_reason":"manual","fees":"0.0","interest":"0.0"}
+    """
+
+  Scenario: Clients are written as an aligned table
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    2.0
+    deposit,      12,       2,    100.25
+    chargeback,   12,       2,
+    dispute,      12,       2,
+    chargeback,   12,       2,
+    """
+    When the engine is executed with table output
+    Then the following output should be generated
+    """
+    client  available  held  total  locked  lock_reason  fees  interest
+    1       2.0        0.0   2.0    false                0.0   0.0
+    12      0.0        0.0   0.0    true    chargeback   0.0   0.0
+    """
@@ -0,0 +1,70 @@
+Feature: A simple toy payments engine dispute a part of a transaction
+  Scenario: Dispute and resolve a part of a deposit
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    10.0
+    dispute,    1,        1,    4.0
+    resolve,    1,        1,    1.5
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,7.5,2.5,10.0,false,,0.0,0.0
+    """
+
+  Scenario: Chargeback a part of a disputed deposit
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    10.0
+    dispute,    1,        1,    4.0
+    chargeback, 1,        1,    3.0
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,6.0,1.0,7.0,true,chargeback,0.0,0.0
+    """
+
+  Scenario: Cannot dispute more than the remaining amount
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    10.0
+    dispute,    1,        1,    4.0
+    dispute,    1,        1,    7.0
+    resolve,    1,        1,    5.0
+    chargeback, 1,        1,    4.5
+    """
+    When the engine is executed with csv rejection report
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,6.0,4.0,10.0,false,,0.0,0.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    1,1,dispute,invalid_amount
+    1,1,resolve,invalid_amount
+    1,1,chargeback,invalid_amount
+    """
+
+  Scenario: Dispute a part of a withdrawal
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    10.0
+    withdrawal, 1,        2,    4.0
+    dispute,    1,        2,    1.0
+    chargeback, 1,        2,
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,7.0,0.0,7.0,false,,0.0,0.0
+    """
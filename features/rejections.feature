@@ -0,0 +1,61 @@
+Feature: A simple toy payments engine reports the rejected events
+  Scenario: Rejected events are reported with the reason
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    withdrawal, 1,        2,    1.5
+    dispute,    1,        3,
+    resolve,    1,        1,
+    dispute,    1,        1,
+    chargeback, 1,        1,
+    deposit,    1,        4,    1.0
+    """
+    When the engine is executed with csv rejection report
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,0.0,0.0,0.0,true,chargeback,0.0,0.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    1,2,withdrawal,insufficient_funds
+    1,3,dispute,unknown_tx
+    1,1,resolve,invalid_state
+    1,4,deposit,account_locked
+    """
+
+  Scenario: Rejected events are reported as NDJSON
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    withdrawal, 1,        1,    1.0
+    """
+    When the engine is executed with ndjson rejection report
+    Then the following rejections should be reported
+    """
+    {"client":1,"tx":1,"type":"withdrawal","reason":"insufficient_funds"}
+    """
+
+  Scenario: Rejected events are written by the binary
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    withdrawal, 1,        2,    1.5
+    dispute,    1,        3,
+    """
+    When the binary is executed with "--rejections {state} {input}"
+    Then the exit code should be 0
+    And the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.0,0.0,1.0,false,,0.0,0.0
+    """
+    And the following should be written to the state file
+    """
+    client,tx,type,reason
+    1,2,withdrawal,insufficient_funds
+    1,3,dispute,unknown_tx
+    """
@@ -10,8 +10,8 @@ Feature: A simple toy payments engine resolve a disputed transaction
     When the engine is executed
     Then the following output should be generated
     """
-    client,available,held,total,locked
-    1,1.0,0.0,1.0,false
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.0,0.0,1.0,false,,0.0,0.0
     """
 
   Scenario: Resolves affect withdraws
@@ -26,8 +26,8 @@ Feature: A simple toy payments engine resolve a disputed transaction
     When the engine is executed
     Then the following output should be generated
     """
-    client,available,held,total,locked
-    1,0.5,0.0,0.5,false
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,0.5,0.0,0.5,false,,0.0,0.0
     """
 
   Scenario: Cannot resolve a not disputed transaction
@@ -41,6 +41,6 @@ Feature: A simple toy payments engine resolve a disputed transaction
     When the engine is executed
     Then the following output should be generated
     """
-    client,available,held,total,locked
-    1,0.5,0.0,0.5,false
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,0.5,0.0,0.5,false,,0.0,0.0
     """
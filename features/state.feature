@@ -0,0 +1,48 @@
+Feature: A simple toy payments engine continuing from an earlier run
+  Scenario: A dispute references a deposit of the previous day
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    2.0
+    deposit,      2,        2,    1.0
+    withdrawal,   1,        3,    0.5
+    """
+    When the binary is executed with "--state-out {state} {input}"
+    Then the exit code should be 0
+    Given the following CSV file replacing the previous one
+    """
+    type,         client,   tx,   amount
+    dispute,      1,        1,
+    deposit,      2,        2,    5.0
+    deposit,      3,        4,    1.0
+    """
+    When the binary is executed with "--state-in {state} --sorted {input}"
+    Then the exit code should be 0
+    And the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,-0.5,2.0,1.5,false,,0.0,0.0
+    2,1.0,0.0,1.0,false,,0.0,0.0
+    3,1.0,0.0,1.0,false,,0.0,0.0
+    """
+
+  Scenario: An invalid state is a parse failure
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    """
+    When the binary is executed with "--state-in {input} {input}"
+    Then the exit code should be 3
+
+  Scenario: An unwritable state is a dump failure
+    Given the following CSV file
+    """
+    type,         client,   tx,   amount
+    deposit,      1,        1,    1.0
+    """
+    When the binary is executed with "--error-format json --state-out missing/state.json {input}"
+    Then the exit code should be 4
+    And the following error should be printed
+    """
+    {"code":4,"error":"dump","message":"State could not be written: No such file or directory (os error 2)"}
+    """
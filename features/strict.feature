@@ -0,0 +1,49 @@
+Feature: A simple toy payments engine treatment of malformed rows
+  Scenario: Malformed rows are skipped in lenient mode
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    deposit,    a,        2,    1.0
+    deposit,    1,        3,    1.5
+    """
+    When the engine is executed in lenient mode
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,2.5,0.0,2.5,false,,0.0,0.0
+    """
+
+  Scenario: Processing is aborted at the first malformed row
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    deposit,    1,        2,    a
+    deposit,    1
+    """
+    When the engine is executed in abort mode
+    Then the engine should fail at line 3
+
+  Scenario: Malformed rows are collected
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount
+    deposit,    1,        1,    1.0
+    deposit,    a,        2,    1.0
+    deposit,    1
+    deposit,    1,        4,    0.00001
+    deposit,    1,        5,    1.5
+    """
+    When the engine is executed in collect mode
+    Then the following rows should be rejected
+    """
+    3: deposit,a,2,1.0
+    4: deposit,1
+    5: deposit,1,4,0.00001
+    """
+    And the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,2.5,0.0,2.5,false,,0.0,0.0
+    """
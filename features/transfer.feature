@@ -0,0 +1,128 @@
+Feature: A simple toy payments engine transfers between clients
+  Scenario: Transfer an amount to another client
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount, destination
+    deposit,    1,        1,    3.0,
+    transfer,   1,        2,    2.0,    2
+    transfer,   2,        3,    0.5,    3
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.0,0.0,1.0,false,,0.0,0.0
+    2,1.5,0.0,1.5,false,,0.0,0.0
+    3,0.5,0.0,0.5,false,,0.0,0.0
+    """
+
+  Scenario: Transfer is rejected on either side
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount, destination
+    deposit,    1,        1,    3.0,
+    transfer,   1,        2,    5.0,    2
+    lock,       3,        3,
+    transfer,   1,        4,    1.0,    3
+    transfer,   1,        5,    1.0,
+    transfer,   1,        6,    1.0,    1
+    transfer,   3,        7,    1.0,    1
+    """
+    When the engine is executed with csv rejection report
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,3.0,0.0,3.0,false,,0.0,0.0
+    3,0.0,0.0,0.0,true,manual,0.0,0.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    1,2,transfer,insufficient_funds
+    1,4,transfer,account_locked
+    1,5,transfer,invalid_destination
+    1,6,transfer,invalid_destination
+    3,7,transfer,account_locked
+    """
+
+  Scenario: Transfer of a non-positive amount is rejected
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount, destination
+    deposit,    2,        1,    100.0,
+    transfer,   1,        2,    -100.0, 2
+    transfer,   2,        3,    0,      1
+    withdrawal, 1,        4,    100.0,
+    """
+    When the engine is executed with csv rejection report
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,0.0,0.0,0.0,false,,0.0,0.0
+    2,100.0,0.0,100.0,false,,0.0,0.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    1,2,transfer,invalid_amount
+    2,3,transfer,invalid_amount
+    1,4,withdrawal,insufficient_funds
+    """
+
+  Scenario: Dispute a transfer
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount, destination
+    deposit,    1,        1,    3.0,
+    transfer,   1,        2,    2.0,    2
+    dispute,    1,        2,    ,
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.0,2.0,3.0,false,,0.0,0.0
+    2,0.0,2.0,2.0,false,,0.0,0.0
+    """
+
+  Scenario: Charge back a transfer
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount, destination
+    deposit,    1,        1,    3.0,
+    transfer,   1,        2,    2.0,    2
+    dispute,    1,        2,    1.5,
+    resolve,    1,        2,    0.5,
+    chargeback, 1,        2,    ,
+    """
+    When the engine is executed
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,2.0,0.0,2.0,false,,0.0,0.0
+    2,1.0,0.0,1.0,true,chargeback,0.0,0.0
+    """
+
+  Scenario: Dispute of a transfer is rejected, if the destination cannot cover it
+    Given the following CSV file
+    """
+    type,       client,   tx,   amount, destination
+    deposit,    1,        1,    3.0,
+    transfer,   1,        2,    2.0,    2
+    withdrawal, 2,        3,    1.5,
+    dispute,    1,        2,    ,
+    dispute,    2,        2,    ,
+    """
+    When the engine is executed with forbid negative balance policy
+    Then the following output should be generated
+    """
+    client,available,held,total,locked,lock_reason,fees,interest
+    1,1.0,0.0,1.0,false,,0.0,0.0
+    2,0.5,0.0,0.5,false,,0.0,0.0
+    """
+    And the following rejections should be reported
+    """
+    client,tx,type,reason
+    1,2,dispute,insufficient_funds
+    2,2,dispute,foreign_tx
+    """
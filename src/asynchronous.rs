@@ -0,0 +1,259 @@
+use crate::error::KrctError;
+use crate::pool::{Pool, QUEUE_CAPACITY};
+use crate::{Account, Config, Event, Krct, Ledger, OutputFormat, Result, Transaction};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+/// Size of a chunk of the input passed from the async reader to the dispatcher
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Blocking reader of the chunks of the async input, so the dispatcher parses the input with the
+/// same CSV reader as the sync engine, and a quoted field could span more lines
+struct Chunks {
+    receiver: mpsc::Receiver<std::io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    offset: usize,
+}
+
+impl std::io::Read for Chunks {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        while self.offset == self.chunk.len() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk?;
+                    self.offset = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let length = buffer.len().min(self.chunk.len() - self.offset);
+        buffer[..length].copy_from_slice(&self.chunk[self.offset..self.offset + length]);
+        self.offset += length;
+
+        Ok(length)
+    }
+}
+
+impl Krct {
+    /// Reads the given async input CSV stream with the given configuration, like
+    /// `Krct::read_with`. The clients are handled by tokio tasks, and the input is parsed and
+    /// dispatched to them by a blocking task, so it has to be called on a multi-threaded tokio
+    /// runtime.
+    ///
+    /// # Error
+    /// With `Strictness::Abort` the first malformed row stops the processing. If the dispatcher
+    /// task died, all the clients are lost.
+    pub async fn read_async<R: AsyncRead + Unpin>(mut reader: R, config: Config) -> Result<Self> {
+        let mut pool = Pool::new_async(&config);
+        let capacity = config.queue_capacity.unwrap_or(QUEUE_CAPACITY).max(1);
+        let (chunks, receiver) = mpsc::channel(capacity);
+        let strictness = config.strictness;
+        let dispatcher = tokio::task::spawn_blocking(move || {
+            let mut rejected_rows = Vec::new();
+            let mut reader = Self::get_reader(Chunks {
+                receiver,
+                chunk: Vec::new(),
+                offset: 0,
+            });
+            for (_, row) in Self::deserialize::<Transaction, _>(&mut reader, 0)? {
+                // A transaction of an unknown type is ignored
+                let event = Self::accept(row, strictness, &mut rejected_rows)?
+                    .and_then(|tx| Event::try_from(tx).ok());
+                if let Some(event) = event {
+                    pool.handle(event)?;
+                }
+            }
+            Ok::<_, KrctError>(Krct {
+                pool,
+                rejected_rows,
+            })
+        });
+
+        // The reading stops, if the dispatcher stops
+        loop {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            let chunk = match reader.read(&mut chunk).await {
+                Ok(0) => break,
+                Ok(length) => {
+                    chunk.truncate(length);
+                    Ok(chunk)
+                }
+                Err(err) => Err(err),
+            };
+            let failed = chunk.is_err();
+            if chunks.send(chunk).await.is_err() || failed {
+                break;
+            }
+        }
+        drop(chunks);
+
+        dispatcher
+            .await
+            .map_err(|_| KrctError::Failed(Vec::new()))?
+    }
+
+    /// Current balances of the given client like `Krct::balance`, without blocking the other
+    /// tasks of the async runtime
+    pub async fn balance_async(&self, client: u16) -> Result<Option<Account>> {
+        tokio::task::block_in_place(|| self.balance(client))
+    }
+
+    /// Consistent point-in-time view of all the clients like `Krct::snapshot`, without blocking
+    /// the other tasks of the async runtime
+    pub async fn snapshot_async(&self) -> Result<Vec<Account>> {
+        tokio::task::block_in_place(|| self.snapshot())
+    }
+
+    /// Ledger of every client like `Krct::ledger`, without blocking the other tasks of the async
+    /// runtime
+    pub async fn ledger_async(&self) -> Result<Ledger> {
+        tokio::task::block_in_place(|| self.ledger())
+    }
+
+    /// When all events are finished processing, the result dumped to the given async writer.
+    pub async fn dump_async<W: AsyncWrite + Unpin>(self, writer: W) -> Result<()> {
+        self.dump_as_async(writer, OutputFormat::Csv).await
+    }
+
+    /// Dumps the result set sorted by the client identifier to the given async writer
+    pub async fn dump_sorted_async<W: AsyncWrite + Unpin>(self, writer: W) -> Result<()> {
+        self.dump_sorted_as_async(writer, OutputFormat::Csv).await
+    }
+
+    /// Dumps the result to the given async writer in the given format, like `Krct::dump_as`
+    pub async fn dump_as_async<W: AsyncWrite + Unpin>(
+        self,
+        writer: W,
+        format: OutputFormat,
+    ) -> Result<()> {
+        Self::write_async(self.pool.join_async().await, writer, format).await
+    }
+
+    /// Dumps the result set sorted by the client identifier to the given async writer in the
+    /// given format, like `Krct::dump_sorted_as`
+    pub async fn dump_sorted_as_async<W: AsyncWrite + Unpin>(
+        self,
+        writer: W,
+        format: OutputFormat,
+    ) -> Result<()> {
+        let clients = Pool::sort(self.pool.join_async().await);
+        Self::write_async(clients, writer, format).await
+    }
+
+    /// Writes the given clients to the given async writer in the given format, the failed clients
+    /// are skipped. There are at most 65536 clients, so the output is formatted in memory, and
+    /// written at once.
+    ///
+    /// # Error
+    /// If the writing fails, or the ids of the failed clients after all the others are written.
+    async fn write_async<I, T, W>(clients: I, mut writer: W, format: OutputFormat) -> Result<()>
+    where
+        I: IntoIterator<Item = Result<T>>,
+        T: serde::Serialize,
+        W: AsyncWrite + Unpin,
+    {
+        let mut buffer = Vec::new();
+        let result = Self::write(clients, &mut buffer, format);
+        writer
+            .write_all(&buffer)
+            .await
+            .map_err(KrctError::CsvDump)?;
+        writer.flush().await.map_err(KrctError::CsvDump)?;
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, Krct, KrctError, OutputFormat, Strictness};
+
+    const INPUT: &str = "\
+        type,client,tx,amount\n\
+        deposit,1,1,2.0\n\
+        deposit,2,2,1.0\n\
+        \n\
+        withdrawal,1,3,a\n\
+        deposit,3,5,\"1.0\n\"\n\
+        withdrawal,1,4,0.5\n\
+        withdrawal,1,6,b\n";
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_async_engine_matches_sync_engine() {
+        let config = Config {
+            strictness: Strictness::Collect,
+            ..Config::default()
+        };
+        let krct = Krct::read_async(INPUT.as_bytes(), config.clone())
+            .await
+            .expect("Failed to read input");
+        let rejected_rows = krct
+            .rejected_rows()
+            .iter()
+            .map(|row| row.line)
+            .collect::<Vec<_>>();
+        let snapshot = krct
+            .snapshot_async()
+            .await
+            .expect("Failed to take snapshot");
+        let balance = krct
+            .balance_async(1)
+            .await
+            .expect("Failed to query balance");
+        assert_eq!(snapshot.first(), balance.as_ref());
+        // The quoted field spans two lines, but it is a single record
+        assert_eq!(snapshot.len(), 3);
+        let mut output = Vec::new();
+        krct.dump_sorted_async(&mut output)
+            .await
+            .expect("Failed to write output");
+
+        let krct = Krct::read_with(INPUT.as_bytes(), config).expect("Failed to read input");
+        assert_eq!(
+            krct.rejected_rows()
+                .iter()
+                .map(|row| row.line)
+                .collect::<Vec<_>>(),
+            rejected_rows
+        );
+        assert_eq!(krct.snapshot().ok(), Some(snapshot));
+        let mut expected = Vec::new();
+        krct.dump_sorted(&mut expected)
+            .expect("Failed to write output");
+        assert_eq!(output, expected);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_async_dump_formats_match_sync_dump() {
+        for format in [
+            OutputFormat::Csv,
+            OutputFormat::Json,
+            OutputFormat::Ndjson,
+            OutputFormat::Table,
+        ] {
+            let krct = Krct::read_async(INPUT.as_bytes(), Config::default())
+                .await
+                .expect("Failed to read input");
+            let mut output = Vec::new();
+            krct.dump_sorted_as_async(&mut output, format)
+                .await
+                .expect("Failed to write output");
+
+            let krct =
+                Krct::read_with(INPUT.as_bytes(), Config::default()).expect("Failed to read input");
+            let mut expected = Vec::new();
+            krct.dump_sorted_as(&mut expected, format)
+                .expect("Failed to write output");
+            assert_eq!(output, expected, "{:?}", format);
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_sync_dump_on_current_thread_runtime_fails() {
+        let krct = Krct::read_async(INPUT.as_bytes(), Config::default())
+            .await
+            .expect("Failed to read input");
+        let mut output = Vec::new();
+        assert!(matches!(krct.dump(&mut output), Err(KrctError::Runtime)));
+    }
+}
@@ -0,0 +1,289 @@
+use crate::pool::{Amount, Event, Reason};
+
+/// Describes how the engine treats malformed input rows, e.g. a non-numeric client id, a missing
+/// column or an invalid amount
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Strictness {
+    /// Malformed rows are silently skipped
+    #[default]
+    Lenient,
+    /// Processing is stopped at the first malformed row with an error
+    Abort,
+    /// Malformed rows are skipped, but collected, so they could be inspected after processing
+    Collect,
+}
+
+impl std::str::FromStr for Strictness {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "lenient" => Ok(Strictness::Lenient),
+            "abort" => Ok(Strictness::Abort),
+            "collect" => Ok(Strictness::Collect),
+            unknown => Err(format!("Unknown strictness: {}", unknown)),
+        }
+    }
+}
+
+/// Output format of a report
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ReportFormat {
+    /// Comma separated values with a header line
+    #[default]
+    Csv,
+    /// Newline delimited JSON, one object per line
+    Ndjson,
+}
+
+impl std::str::FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "csv" => Ok(ReportFormat::Csv),
+            "ndjson" => Ok(ReportFormat::Ndjson),
+            unknown => Err(format!("Unknown report format: {}", unknown)),
+        }
+    }
+}
+
+/// Output format of the client accounts
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputFormat {
+    /// Comma separated values with a header line
+    #[default]
+    Csv,
+    /// A single JSON array of objects
+    Json,
+    /// Newline delimited JSON, one object per line
+    Ndjson,
+    /// Human-readable table with aligned columns for terminal use
+    Table,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "table" => Ok(OutputFormat::Table),
+            unknown => Err(format!("Unknown output format: {}", unknown)),
+        }
+    }
+}
+
+/// Format of an error printed by the binary
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ErrorFormat {
+    /// Human-readable message
+    #[default]
+    Text,
+    /// A single JSON object with the kind, the exit code and the message of the error
+    Json,
+}
+
+impl std::str::FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            unknown => Err(format!("Unknown error format: {}", unknown)),
+        }
+    }
+}
+
+/// Describes what happens, when a dispute would hold more than the available amount, e.g. the
+/// disputed deposit has been already withdrawn, or a fee would charge more than the available
+/// amount
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NegativeBalance {
+    /// The event is rejected with insufficient funds
+    Forbid,
+    /// The whole amount is taken, the available amount goes negative
+    #[default]
+    Allow,
+    /// Only the available amount is taken, the event is rejected if nothing is available
+    Cap,
+}
+
+impl NegativeBalance {
+    /// The amount, which could be taken from the given available amount according to the policy
+    ///
+    /// # Error
+    /// If the policy does not allow to take anything
+    pub(crate) fn limit(self, available: Amount, amount: Amount) -> Result<Amount, Reason> {
+        match self {
+            _ if available >= amount => Ok(amount),
+            NegativeBalance::Allow => Ok(amount),
+            NegativeBalance::Cap if available > Amount::ZERO => Ok(available),
+            NegativeBalance::Cap | NegativeBalance::Forbid => Err(Reason::InsufficientFunds),
+        }
+    }
+}
+
+impl std::str::FromStr for NegativeBalance {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "forbid" => Ok(NegativeBalance::Forbid),
+            "allow" => Ok(NegativeBalance::Allow),
+            "cap" => Ok(NegativeBalance::Cap),
+            unknown => Err(format!("Unknown negative balance policy: {}", unknown)),
+        }
+    }
+}
+
+/// Describes which event types are still accepted by a locked account. By default, nothing is
+/// accepted. An unlock is always accepted, while a lock is never accepted by a locked account.
+/// The sides of a transfer are treated as a withdrawal and a deposit.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LockedPolicy {
+    pub deposit: bool,
+    pub withdrawal: bool,
+    pub dispute: bool,
+    pub resolve: bool,
+    pub chargeback: bool,
+    pub fee: bool,
+    pub interest: bool,
+}
+
+impl LockedPolicy {
+    /// Whether a locked account accepts the given event
+    pub(crate) fn accepts(&self, event: &Event) -> bool {
+        match event {
+            Event::Deposit { .. } => self.deposit,
+            Event::Withdrawal { .. } => self.withdrawal,
+            Event::Dispute { .. } => self.dispute,
+            Event::Resolve { .. } => self.resolve,
+            Event::Chargeback { .. } => self.chargeback,
+            Event::Fee { .. } => self.fee,
+            Event::Interest { .. } => self.interest,
+            Event::Unlock { .. } => true,
+            Event::Lock { .. } | Event::Transfer { .. } => false,
+        }
+    }
+}
+
+impl std::str::FromStr for LockedPolicy {
+    type Err = String;
+
+    /// Parses a comma separated list of the accepted event types, e.g. `deposit,dispute,resolve`.
+    /// `none` or an empty list accepts nothing.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut policy = LockedPolicy::default();
+        for event_type in value.split(',').map(str::trim) {
+            match event_type {
+                "" | "none" => (),
+                "deposit" => policy.deposit = true,
+                "withdrawal" => policy.withdrawal = true,
+                "dispute" => policy.dispute = true,
+                "resolve" => policy.resolve = true,
+                "chargeback" => policy.chargeback = true,
+                "fee" => policy.fee = true,
+                "interest" => policy.interest = true,
+                unknown => return Err(format!("Unknown event type: {}", unknown)),
+            }
+        }
+
+        Ok(policy)
+    }
+}
+
+/// Business rules applied by each client
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    /// Treatment of disputes exceeding the available amount
+    pub negative_balance: NegativeBalance,
+    /// Event types accepted by a locked account
+    pub locked: LockedPolicy,
+    /// Treatment of fees exceeding the available amount
+    pub fee: NegativeBalance,
+}
+
+/// Configuration of the engine
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Treatment of malformed input rows
+    pub strictness: Strictness,
+    /// Collect the rejected events, see `Krct::rejections`
+    pub rejections: bool,
+    /// Record the applied events of every client, see `Krct::ledger`. The events of a state the
+    /// engine is restored from are not recorded, the ledger starts from the restored balances.
+    pub ledger: bool,
+    /// Business rules applied by the clients
+    pub policy: Policy,
+    /// Number of worker threads handling the clients, by default the number of CPUs
+    pub workers: Option<usize>,
+    /// Number of events queued for a worker before the reader blocks, by default 1024
+    pub queue_capacity: Option<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Amount, Event, LockedPolicy, NegativeBalance, Reason};
+
+    #[test]
+    fn test_covered_amount_is_taken_by_all_policies() {
+        let (available, amount) = (Amount::from(2.0), Amount::from(1.0));
+        assert_eq!(NegativeBalance::Forbid.limit(available, amount), Ok(amount));
+        assert_eq!(NegativeBalance::Allow.limit(available, amount), Ok(amount));
+        assert_eq!(NegativeBalance::Cap.limit(available, amount), Ok(amount));
+    }
+
+    #[test]
+    fn test_not_covered_amount() {
+        let (available, amount) = (Amount::from(1.0), Amount::from(2.0));
+        assert_eq!(
+            NegativeBalance::Forbid.limit(available, amount),
+            Err(Reason::InsufficientFunds)
+        );
+        assert_eq!(NegativeBalance::Allow.limit(available, amount), Ok(amount));
+        assert_eq!(NegativeBalance::Cap.limit(available, amount), Ok(available));
+        assert_eq!(
+            NegativeBalance::Cap.limit(Amount::ZERO, amount),
+            Err(Reason::InsufficientFunds)
+        );
+    }
+
+    #[test]
+    fn test_locked_policy_parsing() {
+        assert_eq!("none".parse(), Ok(LockedPolicy::default()));
+        assert_eq!(
+            "deposit, dispute,resolve".parse(),
+            Ok(LockedPolicy {
+                deposit: true,
+                dispute: true,
+                resolve: true,
+                ..LockedPolicy::default()
+            })
+        );
+        assert!("deposit,transfer".parse::<LockedPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_locked_policy_accepts() {
+        let policy = LockedPolicy {
+            deposit: true,
+            ..LockedPolicy::default()
+        };
+        assert!(policy.accepts(&Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(1.0)
+        }));
+        assert!(!policy.accepts(&Event::Withdrawal {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(1.0)
+        }));
+        assert!(policy.accepts(&Event::Unlock { client: 1, tx: 1 }));
+        assert!(!policy.accepts(&Event::Lock { client: 1, tx: 1 }));
+    }
+}
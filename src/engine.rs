@@ -0,0 +1,349 @@
+use crate::pool::{Event, Pool};
+use crate::{
+    Account, Config, Journal, Krct, Ledger, MalformedRow, Position, Rejections, Result, RowError,
+    State, Strictness, Transaction,
+};
+
+/// Columns of a submitted record in order, the destination is needed only by a transfer
+const HEADERS: [&str; 5] = ["type", "client", "tx", "amount", "destination"];
+
+/// Incremental payments engine, the events are submitted one by one from any source, e.g. a
+/// message queue, instead of reading a whole input at construction. The workers are started at
+/// once, and each event is dispatched to the worker of its client as soon as it is submitted.
+pub struct Engine {
+    pool: Pool,
+    strictness: Strictness,
+    headers: csv::StringRecord,
+    records: u64,
+    rejected_rows: Vec<MalformedRow>,
+    journal: Option<Journal>,
+    /// Position after the last submitted row, the inputs before it are skipped
+    position: Position,
+    /// Number of the inputs started so far
+    inputs: usize,
+}
+
+impl Engine {
+    /// Constructing a new engine with the given configuration
+    pub fn new(config: Config) -> Self {
+        Self {
+            pool: Pool::new(&config),
+            strictness: config.strictness,
+            headers: csv::StringRecord::from(HEADERS.to_vec()),
+            records: 0,
+            rejected_rows: Vec::new(),
+            journal: None,
+            position: Position::default(),
+            inputs: 0,
+        }
+    }
+
+    /// Constructing a new engine with the given configuration, which continues from the given
+    /// state of an earlier run instead of zero balances
+    ///
+    /// # Error
+    /// If a worker is not running anymore
+    pub fn from_state(config: Config, state: State) -> Result<Self> {
+        let mut engine = Self::new(config);
+        engine.pool.restore(state)?;
+
+        Ok(engine)
+    }
+
+    /// Journals the events submitted from now on to the given journal, so the engine could be
+    /// recovered by `Engine::recover`, if the process dies. A checkpoint of the current state is
+    /// written at once, e.g. the state the engine is started from.
+    ///
+    /// # Error
+    /// If a client failed, or the checkpoint could not be written
+    pub fn with_journal(mut self, mut journal: Journal) -> Result<Self> {
+        journal.checkpoint(self.position, self.pool.state()?)?;
+        self.journal = Some(journal);
+
+        Ok(self)
+    }
+
+    /// Recovers an engine died halfway from the journal in the given directory. The state is
+    /// rebuilt from the latest checkpoint and the journaled events after it, and the journal is
+    /// continued. The same inputs have to be submitted again, the rows up to the last journaled
+    /// one are skipped, so reading is resumed at the recorded byte offset, and no event is
+    /// applied twice.
+    ///
+    /// # Error
+    /// If the journal could not be read, or a worker is not running anymore
+    pub fn recover<P: Into<std::path::PathBuf>>(
+        config: Config,
+        directory: P,
+        interval: u64,
+    ) -> Result<Self> {
+        let (journal, checkpoint, entries) = Journal::open(directory, interval)?;
+        let mut engine = match checkpoint {
+            Some(checkpoint) => {
+                let mut engine = Self::from_state(config, checkpoint.state)?;
+                engine.position = checkpoint.position;
+                engine
+            }
+            None => Self::new(config),
+        };
+        for entry in entries {
+            engine.pool.handle(entry.transaction.try_into()?)?;
+            engine.position = entry.position;
+        }
+        engine.journal = Some(journal);
+
+        Ok(engine)
+    }
+
+    /// Position after the last submitted row of the inputs
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Submits all the rows of the given input CSV streams in order, see `Krct::read_all`. The
+    /// rows up to the position of a recovered engine are skipped. It could be called once per
+    /// input, the inputs are counted across the calls, e.g. for the index of a malformed row.
+    ///
+    /// # Error
+    /// If an input could not be read, or with `Strictness::Abort` if a row is malformed.
+    pub fn submit_all<I, R>(&mut self, inputs: I) -> Result<()>
+    where
+        I: IntoIterator<Item = R>,
+        R: std::io::Read,
+    {
+        for reader in inputs {
+            let input = self.inputs;
+            self.inputs += 1;
+            if input < self.position.input {
+                continue;
+            }
+            let mut reader = Krct::get_reader(reader);
+            if input == self.position.input {
+                Self::skip(&mut reader, self.position.offset)?;
+            }
+            for (offset, row) in Krct::deserialize::<Transaction, R>(&mut reader, input)? {
+                self.position = Position { input, offset };
+                self.submit_row(row)?;
+            }
+        }
+
+        self.flush()
+    }
+
+    /// Writes the journaled events to the disk, and waits until they are stored. The events are
+    /// buffered in between, `submit_all` flushes them after the inputs, and so does a checkpoint.
+    /// If the events are submitted one by one, it should be called at the cadence they have to be
+    /// kept at, e.g. after each batch of a message queue.
+    ///
+    /// # Error
+    /// If the journal could not be written
+    pub fn flush(&mut self) -> Result<()> {
+        match self.journal.as_mut() {
+            Some(journal) => journal.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Skips the rows of the input before the given byte offset
+    fn skip<R: std::io::Read>(reader: &mut csv::Reader<R>, offset: u64) -> Result<()> {
+        reader.headers()?;
+        let mut record = csv::ByteRecord::new();
+        while reader.position().byte() < offset && reader.read_byte_record(&mut record)? {}
+
+        Ok(())
+    }
+
+    /// Submits a single event to the worker of its client. With a journal the event is journaled
+    /// first, and a checkpoint is written, when it is due.
+    ///
+    /// # Error
+    /// If the worker of the client is not running anymore, or the event could not be journaled
+    pub fn submit(&mut self, event: Event) -> Result<()> {
+        let due = match self.journal.as_mut() {
+            Some(journal) => journal.append(self.position, &event)?,
+            None => false,
+        };
+        self.pool.handle(event)?;
+        if let (true, Some(journal)) = (due, self.journal.as_mut()) {
+            journal.checkpoint(self.position, self.pool.state()?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Submits a single CSV record without a header row, the columns are `type`, `client`, `tx`,
+    /// `amount` and the optional `destination`. A malformed record is treated according to the
+    /// strictness of the configuration, its line number is the position of the record, or the
+    /// count of the submitted records, if the record has no position.
+    ///
+    /// # Error
+    /// If the worker of the client is not running anymore, or with `Strictness::Abort` if the
+    /// record is malformed.
+    pub fn submit_record(&mut self, record: &csv::StringRecord) -> Result<()> {
+        self.records += 1;
+        let line = record.position().map_or(self.records, csv::Position::line);
+        let mut record = record.clone();
+        record.trim();
+        let row = record
+            .deserialize::<Transaction>(Some(&self.headers))
+            .map_err(|error| RowError::new(error, &record, line));
+
+        self.submit_row(row)
+    }
+
+    /// Submits a parsed row, which is treated according to the strictness of the configuration
+    pub(crate) fn submit_row(
+        &mut self,
+        row: std::result::Result<Transaction, RowError>,
+    ) -> Result<()> {
+        match Krct::accept(row, self.strictness, &mut self.rejected_rows)? {
+            // A transaction of an unknown type is ignored
+            Some(tx) => match Event::try_from(tx) {
+                Ok(event) => self.submit(event),
+                Err(_) => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// Finishes the submission, no more events are expected. The returned engine could be
+    /// dumped, while the workers are still processing the already submitted events.
+    pub fn finish(self) -> Krct {
+        Krct {
+            pool: self.pool,
+            rejected_rows: self.rejected_rows,
+        }
+    }
+
+    /// Total time spent blocked on submitting events, because the queue of a worker was full
+    pub fn blocked(&self) -> std::time::Duration {
+        self.pool.blocked()
+    }
+
+    /// Malformed records collected with `Strictness::Collect`
+    pub fn rejected_rows(&self) -> &[MalformedRow] {
+        &self.rejected_rows
+    }
+
+    /// Stream of the events rejected by the clients, see `Krct::rejections`
+    pub fn rejections(&mut self) -> Option<Rejections> {
+        self.pool.rejections()
+    }
+
+    /// Current balances of the given client, see `Krct::balance`
+    pub fn balance(&self, client: u16) -> Result<Option<Account>> {
+        self.pool.balance(client)
+    }
+
+    /// Consistent point-in-time view of all the clients, see `Krct::snapshot`
+    pub fn snapshot(&self) -> Result<Vec<Account>> {
+        self.pool.snapshot()
+    }
+
+    /// Full state of the engine, see `Krct::state`
+    pub fn state(&self) -> Result<State> {
+        self.pool.state()
+    }
+
+    /// Ledger of every client, see `Krct::ledger`
+    pub fn ledger(&self) -> Result<Ledger> {
+        self.pool.ledger()
+    }
+
+    /// Finishes the submission, and dumps the result to the given writer, see `Krct::dump`
+    pub fn dump<W: std::io::Write>(self, writer: W) -> Result<()> {
+        self.finish().dump(writer)
+    }
+
+    /// Finishes the submission, and dumps the result sorted by the client identifier, see
+    /// `Krct::dump_sorted`
+    pub fn dump_sorted<W: std::io::Write>(self, writer: W) -> Result<()> {
+        self.finish().dump_sorted(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Engine;
+    use crate::{Amount, Config, Event, KrctError, Strictness};
+
+    #[test]
+    fn test_submitting_events_and_records() {
+        let mut engine = Engine::new(Config {
+            strictness: Strictness::Collect,
+            ..Config::default()
+        });
+        assert!(engine
+            .submit(Event::deposit(1, 1, Amount::from(2.0)))
+            .is_ok());
+        assert!(engine
+            .submit_record(&csv::StringRecord::from(vec![
+                "withdrawal",
+                " 1",
+                "2",
+                "0.5 "
+            ]))
+            .is_ok());
+        assert!(engine
+            .submit_record(&csv::StringRecord::from(vec![
+                "transfer", "1", "3", "1.0", "2"
+            ]))
+            .is_ok());
+        assert!(engine
+            .submit_record(&csv::StringRecord::from(vec!["deposit", "x", "4", "1.0"]))
+            .is_ok());
+        assert_eq!(
+            engine
+                .rejected_rows()
+                .iter()
+                .map(|row| row.line)
+                .collect::<Vec<_>>(),
+            vec![3]
+        );
+
+        let mut output = Vec::new();
+        assert!(engine.dump_sorted(&mut output).is_ok());
+        assert_eq!(
+            String::from_utf8_lossy(&output),
+            "client,available,held,total,locked,lock_reason,fees,interest\n\
+            1,0.5,0.0,0.5,false,,0.0,0.0\n\
+            2,1.0,0.0,1.0,false,,0.0,0.0\n"
+        );
+    }
+
+    #[test]
+    fn test_unknown_record_is_ignored() {
+        let mut engine = Engine::new(Config::default());
+        for record in [
+            vec!["deposit", "1", "1", "1.0"],
+            vec!["unknown", "1", "2", "1.0"],
+            vec!["deposit", "1", "3", "1.0"],
+        ] {
+            assert!(engine
+                .submit_record(&csv::StringRecord::from(record))
+                .is_ok());
+        }
+        assert!(engine.rejected_rows().is_empty());
+
+        let mut output = Vec::new();
+        assert!(engine.dump(&mut output).is_ok());
+        assert_eq!(
+            String::from_utf8_lossy(&output),
+            "client,available,held,total,locked,lock_reason,fees,interest\n\
+            1,2.0,0.0,2.0,false,,0.0,0.0\n"
+        );
+    }
+
+    #[test]
+    fn test_malformed_record_aborts() {
+        let mut engine = Engine::new(Config {
+            strictness: Strictness::Abort,
+            ..Config::default()
+        });
+        let result = engine.submit_record(&csv::StringRecord::from(vec!["deposit", "1"]));
+        assert!(
+            matches!(&result, Err(KrctError::MalformedRow(row)) if row.line == 1),
+            "{:?}",
+            result
+        );
+    }
+}
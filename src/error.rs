@@ -1,11 +1,100 @@
 #[derive(thiserror::Error, Debug)]
 pub enum KrctError {
+    #[error("Input {} could not be opened: {error}", path.display())]
+    Input {
+        path: std::path::PathBuf,
+        #[source]
+        error: std::io::Error,
+    },
     #[error("Input file error: {0}")]
     CsvFileRead(#[from] csv::Error),
     #[error("Dump error: {0}")]
-    CsvDump(#[from] std::io::Error),
-    #[error("Error handling even: {0}")]
-    Handler(#[from] std::sync::mpsc::SendError<crate::Event>),
-    #[error("Unknown even: {0:?}")]
-    UnkUnknownEvent(super::Event),
+    CsvDump(std::io::Error),
+    #[error("Worker of client {0} is not running")]
+    Worker(u16),
+    /// The ids of the failed clients, or none, if all the clients are lost
+    #[error("Failed clients: {}", clients(.0))]
+    Failed(Vec<u16>),
+    #[error("Unknown transaction type")]
+    UnknownType,
+    #[error("Malformed row {0}")]
+    MalformedRow(MalformedRow),
+    #[error("Invalid state: {0}")]
+    StateRead(serde_json::Error),
+    #[error("State could not be written: {0}")]
+    StateWrite(std::io::Error),
+    #[error("Journal error: {0}")]
+    Journal(std::io::Error),
+    /// The async engine could be joined only by blocking a multi-threaded tokio runtime
+    #[error(
+        "Async engine has to be dumped by an async dump, or on a multi-threaded tokio runtime"
+    )]
+    Runtime,
+}
+
+impl KrctError {
+    /// Exit code of the binary failing with the error
+    /// * 2: an input could not be opened or read
+    /// * 3: an input could not be parsed
+    /// * 4: the result, the state or the journal could not be written
+    /// * 5: a worker or a client failed
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            KrctError::Input { .. } => 2,
+            KrctError::CsvFileRead(err) if matches!(err.kind(), csv::ErrorKind::Io(_)) => 2,
+            KrctError::StateRead(err) if err.is_io() => 2,
+            KrctError::CsvFileRead(_)
+            | KrctError::UnknownType
+            | KrctError::MalformedRow(_)
+            | KrctError::StateRead(_) => 3,
+            KrctError::CsvDump(_) | KrctError::StateWrite(_) | KrctError::Journal(_) => 4,
+            KrctError::Worker(_) | KrctError::Failed(_) | KrctError::Runtime => 5,
+        }
+    }
+
+    /// Machine-readable kind of the error, one for each exit code: `input`, `parse`, `dump` or
+    /// `worker`
+    pub fn kind(&self) -> &'static str {
+        match self.exit_code() {
+            2 => "input",
+            3 => "parse",
+            4 => "dump",
+            _ => "worker",
+        }
+    }
+}
+
+/// Comma separated ids of the failed clients, or `all`, if all the clients are lost
+fn clients(ids: &[u16]) -> String {
+    if ids.is_empty() {
+        return "all".to_string();
+    }
+
+    ids.iter().map(u16::to_string).collect::<Vec<_>>().join(",")
+}
+
+/// An input row, which could not be parsed as a transaction
+#[derive(thiserror::Error, Debug)]
+#[error("at line {line}: {}: {error}", record.iter().collect::<Vec<_>>().join(","))]
+pub struct MalformedRow {
+    /// Index of the input the row was read from, when more inputs are read by `Krct::read_all` or
+    /// submitted to an `Engine`
+    pub input: usize,
+    /// Line number of the row in the input
+    pub line: u64,
+    /// The raw fields of the row
+    pub record: csv::StringRecord,
+    /// The reason, why the row could not be parsed
+    #[source]
+    pub error: csv::Error,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum AmountError {
+    #[error("Invalid amount: {0}")]
+    Invalid(String),
+    #[error("Amount has more than four fractional digits: {0}")]
+    Precision(String),
+    #[error("Amount is out of range: {0}")]
+    Overflow(String),
 }
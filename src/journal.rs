@@ -0,0 +1,368 @@
+use crate::error::KrctError;
+use crate::{Event, State, Transaction};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufWriter, Write};
+use std::path::PathBuf;
+
+/// File of the journaled events in the journal directory
+const JOURNAL: &str = "journal.ndjson";
+/// File of the latest checkpoint in the journal directory
+const CHECKPOINT: &str = "checkpoint.json";
+/// Temporary file of a checkpoint being written, it replaces the latest checkpoint at once
+const CHECKPOINT_TMP: &str = "checkpoint.json.tmp";
+
+/// Position in the inputs right after the last journaled row, a recovered engine resumes reading
+/// the inputs from here
+#[derive(Debug, Default, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Position {
+    /// Index of the input, counted across all the inputs submitted to the engine
+    pub input: usize,
+    /// Byte offset in the input after the last journaled row
+    pub offset: u64,
+}
+
+/// A journaled event with the position of its row
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct Entry {
+    sequence: u64,
+    #[serde(flatten)]
+    pub(crate) position: Position,
+    #[serde(flatten)]
+    pub(crate) transaction: Transaction,
+}
+
+/// Full state of the engine after the given journal entry
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct Checkpoint {
+    sequence: u64,
+    #[serde(flatten)]
+    pub(crate) position: Position,
+    pub(crate) state: State,
+}
+
+/// Write-ahead journal of the submitted events, so a run died halfway could be recovered instead
+/// of starting over, see `Engine::with_journal` and `Engine::recover`.
+///
+/// The journal directory holds the latest checkpoint of the full state and an append-only NDJSON
+/// journal of the events submitted since. Each event is appended before it is handled, with the
+/// position of its row in the inputs. The workers decide on the events later, so the rejected
+/// events are journaled too, and they are rejected again, when the journal is replayed. After at least `interval` events, once the journal has grown
+/// as large as the previous checkpoint, a new checkpoint replaces it, and the journal is truncated.
+/// So the checkpoints of a growing state do not take more writing than the journal itself. A
+/// checkpoint is written to a temporary file first and renamed, and the journal entries carry a
+/// sequence number, so no event is applied twice, whenever the process dies.
+pub struct Journal {
+    directory: PathBuf,
+    writer: BufWriter<File>,
+    sequence: u64,
+    interval: u64,
+    /// Events journaled since the latest checkpoint
+    pending: u64,
+    /// Bytes journaled since the latest checkpoint
+    written: u64,
+    /// Size of the latest checkpoint
+    checkpointed: u64,
+}
+
+impl Journal {
+    /// Starts a new journal in the given directory, which is created if it does not exist. An
+    /// earlier journal and checkpoint in the directory are discarded. A checkpoint is written
+    /// after at least `interval` events, when the journal is as large as the previous checkpoint.
+    ///
+    /// # Error
+    /// If the directory or the journal could not be created
+    pub fn create<P: Into<PathBuf>>(directory: P, interval: u64) -> crate::Result<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory).map_err(KrctError::Journal)?;
+        match std::fs::remove_file(directory.join(CHECKPOINT)) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                return Err(KrctError::Journal(err))
+            }
+            _ => (),
+        }
+        let file = Self::open_file(&directory)?;
+        file.set_len(0).map_err(KrctError::Journal)?;
+
+        Ok(Self::new(directory, file, 0, interval))
+    }
+
+    /// Opens an existing journal in the given directory to continue it, with the latest
+    /// checkpoint and the journal entries after it. A torn entry at the end of the journal, i.e.
+    /// the process died while writing it, is dropped.
+    ///
+    /// # Error
+    /// If the journal could not be read, or the checkpoint is invalid
+    pub(crate) fn open<P: Into<PathBuf>>(
+        directory: P,
+        interval: u64,
+    ) -> crate::Result<(Self, Option<Checkpoint>, Vec<Entry>)> {
+        let directory = directory.into();
+        let mut size = 0;
+        let checkpoint = match File::open(directory.join(CHECKPOINT)) {
+            Ok(file) => {
+                size = file.metadata().map_err(KrctError::Journal)?.len();
+                Some(
+                    serde_json::from_reader::<_, Checkpoint>(std::io::BufReader::new(file))
+                        .map_err(KrctError::StateRead)?,
+                )
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => return Err(KrctError::Journal(err)),
+        };
+        let file = Self::open_file(&directory)?;
+
+        let mut reader = std::io::BufReader::new(&file);
+        let mut entries = Vec::new();
+        let mut valid = 0;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let length = reader.read_line(&mut line).map_err(KrctError::Journal)?;
+            if length == 0 || !line.ends_with('\n') {
+                break;
+            }
+            match serde_json::from_str::<Entry>(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(_) => break,
+            }
+            valid += length as u64;
+        }
+        file.set_len(valid).map_err(KrctError::Journal)?;
+
+        let checkpointed = checkpoint
+            .as_ref()
+            .map_or(0, |checkpoint| checkpoint.sequence);
+        entries.retain(|entry| entry.sequence > checkpointed);
+        let sequence = entries.last().map_or(checkpointed, |entry| entry.sequence);
+
+        let mut journal = Self::new(directory, file, sequence, interval);
+        journal.pending = entries.len() as u64;
+        journal.written = valid;
+        journal.checkpointed = size;
+
+        Ok((journal, checkpoint, entries))
+    }
+
+    fn new(directory: PathBuf, file: File, sequence: u64, interval: u64) -> Self {
+        Self {
+            directory,
+            writer: BufWriter::new(file),
+            sequence,
+            interval,
+            pending: 0,
+            written: 0,
+            checkpointed: 0,
+        }
+    }
+
+    /// Opens the journal file for appending, it is created if it does not exist
+    fn open_file(directory: &std::path::Path) -> crate::Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(directory.join(JOURNAL))
+            .map_err(KrctError::Journal)
+    }
+
+    /// Appends an event read at the given position.
+    ///
+    /// Returns `true`, if a checkpoint is due.
+    ///
+    /// # Error
+    /// If the event could not be written
+    pub(crate) fn append(&mut self, position: Position, event: &Event) -> crate::Result<bool> {
+        self.sequence += 1;
+        let entry = Entry {
+            sequence: self.sequence,
+            position,
+            transaction: Transaction::from(event),
+        };
+        let mut line = serde_json::to_vec(&entry).map_err(|err| KrctError::Journal(err.into()))?;
+        line.push(b'\n');
+        self.writer.write_all(&line).map_err(KrctError::Journal)?;
+        self.pending += 1;
+        self.written += line.len() as u64;
+
+        // A checkpoint writes the full state, so it waits until the journal is as large as the
+        // previous one, otherwise the checkpoints of a growing state would take quadratic time
+        Ok(self.interval > 0 && self.pending >= self.interval && self.written >= self.checkpointed)
+    }
+
+    /// Writes a checkpoint of the given state after all the journaled events, then the journal
+    /// is truncated
+    ///
+    /// # Error
+    /// If the checkpoint could not be written
+    pub(crate) fn checkpoint(&mut self, position: Position, state: State) -> crate::Result<()> {
+        // The journal is kept on the disk, if the process dies while the checkpoint is written
+        self.flush()?;
+        let checkpoint = Checkpoint {
+            sequence: self.sequence,
+            position,
+            state,
+        };
+        let path = self.directory.join(CHECKPOINT_TMP);
+        let mut writer = BufWriter::new(File::create(&path).map_err(KrctError::Journal)?);
+        serde_json::to_writer(&mut writer, &checkpoint)
+            .map_err(|err| KrctError::Journal(err.into()))?;
+        let file = writer
+            .into_inner()
+            .map_err(|err| KrctError::Journal(err.into_error()))?;
+        file.sync_all().map_err(KrctError::Journal)?;
+        let size = file.metadata().map_err(KrctError::Journal)?.len();
+        std::fs::rename(&path, self.directory.join(CHECKPOINT)).map_err(KrctError::Journal)?;
+        // The rename is stored only with the directory, the journal must not be truncated before
+        File::open(&self.directory)
+            .and_then(|directory| directory.sync_all())
+            .map_err(KrctError::Journal)?;
+
+        // The checkpointed entries are skipped by their sequence, if the process dies before
+        // the journal is truncated
+        self.writer
+            .get_ref()
+            .set_len(0)
+            .map_err(KrctError::Journal)?;
+        self.pending = 0;
+        self.written = 0;
+        self.checkpointed = size;
+
+        Ok(())
+    }
+
+    /// Writes the buffered events to the disk, and waits until they are stored, see
+    /// `Engine::flush`
+    ///
+    /// # Error
+    /// If the events could not be written
+    pub fn flush(&mut self) -> crate::Result<()> {
+        self.writer.flush().map_err(KrctError::Journal)?;
+        self.writer
+            .get_ref()
+            .sync_data()
+            .map_err(KrctError::Journal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Journal, Position, JOURNAL};
+    use crate::{Config, Engine, Krct};
+    use std::io::Write;
+
+    const INPUT: &str = "type,client,tx,amount,destination\n\
+        deposit,1,1,10.0,\n\
+        deposit,2,2,5.0,\n\
+        withdrawal,1,3,2.5,\n\
+        transfer,1,4,1.0,2\n\
+        dispute,1,1,,\n\
+        deposit,3,5,1.0,\n\
+        resolve,1,1,,\n\
+        withdrawal,2,6,0.5,\n";
+
+    fn dump(engine: Engine) -> String {
+        let mut buffer = Vec::new();
+        engine
+            .finish()
+            .dump_sorted(&mut buffer)
+            .expect("Failed to dump");
+        String::from_utf8(buffer).expect("Invalid output")
+    }
+
+    /// Runs the engine on the first rows of the input, then it dies without a final checkpoint
+    fn crash(directory: &std::path::Path, rows: usize) -> Position {
+        let lines = INPUT.lines().take(rows + 1).collect::<Vec<_>>();
+        let input = lines.join("\n") + "\n";
+        let mut engine = Engine::new(Config::default())
+            .with_journal(Journal::create(directory, 3).expect("Failed to create journal"))
+            .expect("Failed to checkpoint");
+        engine
+            .submit_all([input.as_bytes()])
+            .expect("Failed to submit");
+        engine.position()
+    }
+
+    #[test]
+    fn test_recovery() {
+        let expected = {
+            let mut buffer = Vec::new();
+            Krct::read(INPUT.as_bytes())
+                .and_then(|krct| krct.dump_sorted(&mut buffer))
+                .expect("Failed to run");
+            String::from_utf8(buffer).expect("Invalid output")
+        };
+
+        for rows in [0, 2, 3, 5, 8] {
+            let directory = tempfile::tempdir().expect("Failed to create directory");
+            let position = crash(directory.path(), rows);
+
+            let mut engine =
+                Engine::recover(Config::default(), directory.path(), 3).expect("Failed to recover");
+            assert_eq!(engine.position(), position);
+            engine
+                .submit_all([INPUT.as_bytes()])
+                .expect("Failed to submit");
+            assert_eq!(dump(engine), expected, "crashed after {} rows", rows);
+        }
+    }
+
+    #[test]
+    fn test_recovery_with_torn_entry() {
+        let directory = tempfile::tempdir().expect("Failed to create directory");
+        let position = crash(directory.path(), 4);
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(directory.path().join(JOURNAL))
+            .and_then(|mut journal| journal.write_all(b"{\"sequence\":5,\"inp"))
+            .expect("Failed to tear the journal");
+
+        let engine =
+            Engine::recover(Config::default(), directory.path(), 3).expect("Failed to recover");
+        assert_eq!(engine.position(), position);
+        assert_eq!(
+            dump(engine),
+            "client,available,held,total,locked,lock_reason,fees,interest\n\
+            1,6.5,0.0,6.5,false,,0.0,0.0\n\
+            2,6.0,0.0,6.0,false,,0.0,0.0\n"
+        );
+
+        let journal = std::fs::read_to_string(directory.path().join(JOURNAL))
+            .expect("Failed to read journal");
+        assert!(journal.ends_with('\n'));
+    }
+
+    #[test]
+    fn test_checkpoint_waits_for_the_journal() {
+        let input = (1..=20).fold("type,client,tx,amount\n".to_string(), |input, id| {
+            input + &format!("deposit,{},{},1.0\n", id, id)
+        });
+        let directory = tempfile::tempdir().expect("Failed to create directory");
+        let mut engine = Engine::new(Config::default())
+            .with_journal(Journal::create(directory.path(), 1).expect("Failed to create journal"))
+            .expect("Failed to checkpoint");
+        engine
+            .submit_all([input.as_bytes()])
+            .expect("Failed to submit");
+
+        // The state of many clients is larger than a few entries, so not every event is
+        // checkpointed, and the entries since the last checkpoint are on the disk already
+        let journal = std::fs::read_to_string(directory.path().join(JOURNAL))
+            .expect("Failed to read journal");
+        assert!(journal.lines().count() > 1);
+        assert!(journal.lines().count() < 20);
+        assert!(journal.ends_with('\n'));
+
+        let expected = dump(engine);
+        let engine =
+            Engine::recover(Config::default(), directory.path(), 1).expect("Failed to recover");
+        assert_eq!(dump(engine), expected);
+    }
+
+    #[test]
+    fn test_recovery_without_journal() {
+        let directory = tempfile::tempdir().expect("Failed to create directory");
+        let engine =
+            Engine::recover(Config::default(), directory.path(), 3).expect("Failed to recover");
+        assert_eq!(engine.position(), Position::default());
+    }
+}
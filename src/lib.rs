@@ -1,21 +1,48 @@
+#[cfg(feature = "async")]
+mod asynchronous;
+mod config;
+mod engine;
 mod error;
+mod journal;
+mod output;
 mod pool;
 mod tx;
 
-use crate::pool::{Event, Pool};
-use crate::tx::{Transaction, TransactionType};
+pub use crate::config::{
+    Config, ErrorFormat, LockedPolicy, NegativeBalance, OutputFormat, Policy, ReportFormat,
+    Strictness,
+};
+pub use crate::engine::Engine;
+pub use crate::error::{AmountError, KrctError, MalformedRow};
+pub use crate::journal::{Journal, Position};
+use crate::output::Output;
+use crate::pool::Pool;
+pub use crate::pool::{
+    Account, Amount, Direction, Event, Ledger, LedgerEntry, LockReason, Reason, Rejection,
+    Rejections, State, TransactionState,
+};
+pub use crate::tx::{Transaction, TransactionType};
 
 pub type Result<T> = std::result::Result<T, error::KrctError>;
 
 pub struct Krct {
     pool: Pool,
+    rejected_rows: Vec<MalformedRow>,
 }
 
 impl TryFrom<std::path::PathBuf> for Krct {
     type Error = error::KrctError;
 
     fn try_from(input_file_path: std::path::PathBuf) -> std::result::Result<Self, Self::Error> {
-        let input_file = std::fs::File::open(input_file_path)?;
+        let input_file = match std::fs::File::open(&input_file_path) {
+            Ok(input_file) => input_file,
+            Err(error) => {
+                return Err(KrctError::Input {
+                    path: input_file_path,
+                    error,
+                })
+            }
+        };
         Self::read(input_file)
     }
 }
@@ -30,53 +57,315 @@ impl TryFrom<&std::path::Path> for Krct {
 
 impl Krct {
     /// Reads the given input CSV steam and reads it line by line. Each line is a well defined
-    /// event belongs to a client. Each event processed by the corresponding client thread.
+    /// event belongs to a client. Each event processed by the worker thread of the client.
     pub fn read<R: std::io::Read>(reader: R) -> Result<Self> {
-        let mut reader = Self::get_reader(reader);
-        let mut pool = Pool::default();
+        Self::read_with(reader, Config::default())
+    }
 
-        for tx in Self::deserialize::<Transaction, R>(&mut reader) {
-            pool.handle(tx.into())?
-        }
+    /// Reads the given input CSV stream with the given configuration.
+    ///
+    /// # Error
+    /// With `Strictness::Abort` the first malformed row stops the processing.
+    pub fn read_with<R: std::io::Read>(reader: R, config: Config) -> Result<Self> {
+        Self::read_all(std::iter::once(reader), config)
+    }
+
+    /// Reads the given input CSV streams in order as one continuous stream with the given
+    /// configuration, e.g. daily partitions of the events. Each input starts with its own header
+    /// line, and a malformed row is reported with the index of its input and its line number in
+    /// that input.
+    ///
+    /// # Error
+    /// With `Strictness::Abort` the first malformed row stops the processing.
+    pub fn read_all<I, R>(inputs: I, config: Config) -> Result<Self>
+    where
+        I: IntoIterator<Item = R>,
+        R: std::io::Read,
+    {
+        let mut engine = Engine::new(config);
+        engine.submit_all(inputs)?;
+
+        Ok(engine.finish())
+    }
+
+    /// Total time the reader spent blocked, because the queue of a worker was full. A significant
+    /// time means the workers could not keep up with the reader.
+    pub fn blocked(&self) -> std::time::Duration {
+        self.pool.blocked()
+    }
+
+    /// Malformed input rows collected with `Strictness::Collect`
+    pub fn rejected_rows(&self) -> &[MalformedRow] {
+        &self.rejected_rows
+    }
+
+    /// Stream of the events rejected by the clients, e.g. a withdrawal with insufficient funds.
+    /// Available only once and only if `Config::rejections` is set. The stream ends when the
+    /// engine is dumped, so it should be consumed afterwards or on a separate thread.
+    pub fn rejections(&mut self) -> Option<Rejections> {
+        self.pool.rejections()
+    }
+
+    /// Current balances of the given client, while the events could be still processed. The
+    /// result reflects all the events read so far.
+    ///
+    /// Returns `None`, if the client is not known or it has failed.
+    ///
+    /// # Error
+    /// If the worker of the client is not running anymore
+    pub fn balance(&self, client: u16) -> Result<Option<Account>> {
+        self.pool.balance(client)
+    }
+
+    /// Consistent point-in-time view of all the clients sorted by the client identifier, while
+    /// the events could be still processed. The result reflects all the events read so far, and
+    /// the engine could be still dumped afterwards.
+    ///
+    /// # Error
+    /// If a worker is not running anymore, with the ids of its clients
+    pub fn snapshot(&self) -> Result<Vec<Account>> {
+        self.pool.snapshot()
+    }
+
+    /// Full state of the engine including the transaction histories, while the events could be
+    /// still processed. A later run could be started from it with `Engine::from_state`.
+    ///
+    /// # Error
+    /// If a client failed or a worker is not running anymore, with the ids of the failed clients
+    pub fn state(&self) -> Result<State> {
+        self.pool.state()
+    }
 
-        Ok(Krct { pool })
+    /// Ledger of every client for audits, each applied event of a client in order with the
+    /// resulting balances and the final state of its transaction, while the events could be
+    /// still processed. It is empty, unless `Config::ledger` is enabled.
+    ///
+    /// # Error
+    /// If a client failed or a worker is not running anymore, with the ids of the failed clients
+    pub fn ledger(&self) -> Result<Ledger> {
+        self.pool.ledger()
     }
 
     /// When all events are finished processing, the result dumped to the given writer.
+    ///
+    /// # Error
+    /// If some clients failed, then all the healthy clients are still dumped, and the failed
+    /// client ids are returned in a single error.
     pub fn dump<W: std::io::Write>(self, writer: W) -> Result<()> {
-        let mut writer = csv::Writer::from_writer(writer);
-        for client in self.pool.iter() {
-            writer.serialize(client)?;
-            writer.flush()?;
-        }
-
-        Ok(())
+        self.dump_as(writer, OutputFormat::Csv)
     }
 
     /// Dumps the result set sorted by the client identifier
+    ///
+    /// # Error
+    /// If some clients failed, then all the healthy clients are still dumped, and the failed
+    /// client ids are returned in a single error.
     pub fn dump_sorted<W: std::io::Write>(self, writer: W) -> Result<()> {
-        let mut writer = csv::Writer::from_writer(writer);
-        for client in self.pool.sorted() {
-            writer.serialize(client)?;
-            writer.flush()?;
+        self.dump_sorted_as(writer, OutputFormat::Csv)
+    }
+
+    /// Dumps the result to the given writer in the given format, see `Krct::dump`
+    pub fn dump_as<W: std::io::Write>(self, writer: W, format: OutputFormat) -> Result<()> {
+        Self::write(self.pool.iter(), writer, format)
+    }
+
+    /// Dumps the result set sorted by the client identifier in the given format, see
+    /// `Krct::dump_sorted`
+    pub fn dump_sorted_as<W: std::io::Write>(self, writer: W, format: OutputFormat) -> Result<()> {
+        Self::write(self.pool.sorted(), writer, format)
+    }
+
+    /// Writes the given clients to the given writer in the given format, the failed clients are
+    /// skipped.
+    ///
+    /// # Error
+    /// If the writing fails, or the ids of the failed clients after all the others are written.
+    fn write<I, T, W>(clients: I, writer: W, format: OutputFormat) -> Result<()>
+    where
+        I: IntoIterator<Item = Result<T>>,
+        T: serde::Serialize,
+        W: std::io::Write,
+    {
+        let mut output = Output::new(writer, format);
+        let mut failed = Vec::new();
+        for client in clients {
+            match client {
+                Ok(client) => output.write(client).map_err(KrctError::CsvDump)?,
+                Err(err) => failed.push(Self::failed_client(err)?),
+            }
         }
+        output.finish().map_err(KrctError::CsvDump)?;
 
-        Ok(())
+        Self::failed(failed)
     }
 
-    fn deserialize<'a, T, R>(reader: &'a mut csv::Reader<R>) -> impl Iterator<Item = T> + 'a
+    /// Client id of a failed client
+    ///
+    /// # Error
+    /// The given error itself, if it is not a failure of a client
+    fn failed_client(err: KrctError) -> Result<u16> {
+        match err {
+            KrctError::Worker(client) => Ok(client),
+            err => Err(err),
+        }
+    }
+
+    /// Aggregates the ids of the failed clients into a single error
+    fn failed(mut failed: Vec<u16>) -> Result<()> {
+        if failed.is_empty() {
+            return Ok(());
+        }
+        failed.sort_unstable();
+
+        Err(KrctError::Failed(failed))
+    }
+
+    /// Treats a parsed row according to the given strictness
+    ///
+    /// Returns the transaction of a well-formed row.
+    ///
+    /// # Error
+    /// If the input could not be read, or with `Strictness::Abort` if the row is malformed.
+    fn accept<T>(
+        row: std::result::Result<T, RowError>,
+        strictness: Strictness,
+        rejected_rows: &mut Vec<MalformedRow>,
+    ) -> Result<Option<T>> {
+        match row {
+            Ok(tx) => Ok(Some(tx)),
+            Err(RowError::Io(err)) => Err(err.into()),
+            Err(RowError::Malformed(row)) => match strictness {
+                Strictness::Lenient => Ok(None),
+                Strictness::Abort => Err(KrctError::MalformedRow(row)),
+                Strictness::Collect => {
+                    rejected_rows.push(row);
+                    Ok(None)
+                }
+            },
+        }
+    }
+
+    fn deserialize<'a, T, R>(
+        reader: &'a mut csv::Reader<R>,
+        input: usize,
+    ) -> Result<impl Iterator<Item = (u64, std::result::Result<T, RowError>)> + 'a>
     where
         T: for<'de> serde::Deserialize<'de> + 'a,
         R: std::io::Read,
     {
-        reader
-            .deserialize::<T>()
-            .filter_map(std::result::Result::ok)
+        let headers = reader.headers()?.clone();
+        let mut record = csv::StringRecord::new();
+
+        Ok(std::iter::from_fn(move || {
+            let row = match reader.read_record(&mut record) {
+                Ok(false) => return None,
+                Ok(true) => record.deserialize::<T>(Some(&headers)),
+                Err(err) => Err(err),
+            };
+
+            let line = record.position().map_or(0, csv::Position::line);
+            let row = row.map_err(|error| RowError::new(error, &record, line).in_input(input));
+            Some((reader.position().byte(), row))
+        }))
     }
 
     fn get_reader<R: std::io::Read>(reader: R) -> csv::Reader<R> {
-        csv::ReaderBuilder::new()
-            .trim(csv::Trim::All)
-            .from_reader(reader)
+        Self::reader_builder().from_reader(reader)
+    }
+
+    fn reader_builder() -> csv::ReaderBuilder {
+        let mut builder = csv::ReaderBuilder::new();
+        builder.trim(csv::Trim::All).flexible(true);
+
+        builder
+    }
+}
+
+/// Reason of a row could not be turned into a transaction
+enum RowError {
+    /// The input stream itself could not be read
+    Io(csv::Error),
+    /// The row could be read, but it is not a valid transaction
+    Malformed(MalformedRow),
+}
+
+impl RowError {
+    /// Reason of the given record at the given line could not be parsed
+    fn new(error: csv::Error, record: &csv::StringRecord, line: u64) -> Self {
+        match error.kind() {
+            csv::ErrorKind::Io(_) => RowError::Io(error),
+            _ => RowError::Malformed(MalformedRow {
+                input: 0,
+                line,
+                record: record.clone(),
+                error,
+            }),
+        }
+    }
+
+    /// The same reason of a row read from the given input
+    fn in_input(self, input: usize) -> Self {
+        match self {
+            RowError::Malformed(row) => RowError::Malformed(MalformedRow { input, ..row }),
+            err => err,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, Krct, KrctError, Strictness};
+    use std::io::Write;
+
+    #[test]
+    fn test_reading_more_inputs_as_one_stream() {
+        let inputs = [
+            "type,client,tx,amount\ndeposit,1,1,2.0\ndeposit,1,x,1.0\n",
+            "tx,type,client,amount\n2,withdrawal,1,0.5\n",
+            "type,client,tx,amount\ndispute,1,1,\ndeposit,a,3,1.0\n",
+        ];
+        let krct = Krct::read_all(
+            inputs.iter().map(|input| input.as_bytes()),
+            Config {
+                strictness: Strictness::Collect,
+                ..Config::default()
+            },
+        )
+        .expect("Failed to read inputs");
+        assert_eq!(
+            krct.rejected_rows()
+                .iter()
+                .map(|row| (row.input, row.line))
+                .collect::<Vec<_>>(),
+            vec![(0, 3), (2, 3)]
+        );
+
+        let mut output = Vec::new();
+        assert!(krct.dump_sorted(&mut output).is_ok());
+        assert_eq!(
+            String::from_utf8_lossy(&output),
+            "client,available,held,total,locked,lock_reason,fees,interest\n\
+            1,-0.5,2.0,1.5,false,,0.0,0.0\n"
+        );
+    }
+
+    #[test]
+    fn test_reading_from_path() {
+        let mut input = tempfile::NamedTempFile::new().expect("Failed to create input");
+        input
+            .write_all(b"type,client,tx,amount\ndeposit,1,1,2.0\n")
+            .expect("Failed to write input");
+        let krct = Krct::try_from(input.path()).expect("Failed to read input");
+        assert_eq!(krct.snapshot().map(|accounts| accounts.len()).ok(), Some(1));
+
+        let missing = input.path().with_extension("missing");
+        let result = Krct::try_from(missing.clone());
+        assert!(
+            matches!(&result, Err(KrctError::Input { path, .. }) if *path == missing),
+            "{:?}",
+            result.err()
+        );
+        let error = result.err().expect("Missing input should fail");
+        assert_eq!((error.exit_code(), error.kind()), (2, "input"));
     }
 }
@@ -1,26 +1,308 @@
-use krct::Krct;
+use krct::{
+    Config, Engine, ErrorFormat, Journal, KrctError, LockedPolicy, NegativeBalance, OutputFormat,
+    Policy, ReportFormat, State, Strictness,
+};
 
 #[derive(structopt::StructOpt)]
 #[structopt(name = "krct", about = "A simple toy payments engine.")]
 struct Args {
-    #[structopt(parse(from_os_str))]
-    input: std::path::PathBuf,
+    /// Input CSV files processed in order as one stream, `-` reads stdin, and a directory reads
+    /// its files sorted by name
+    #[structopt(parse(from_os_str), required = true)]
+    inputs: Vec<std::path::PathBuf>,
+    /// Treatment of malformed input rows: skip them silently (lenient), stop at the first one
+    /// (abort) or skip them and report them to stderr (collect)
+    #[structopt(
+        long,
+        default_value = "lenient",
+        possible_values = &["lenient", "abort", "collect"]
+    )]
+    strict: Strictness,
+    /// Writes the rejected events with the reason of the rejection to the given file. With
+    /// `--recover` the report is appended to, the rejections since the latest checkpoint could be
+    /// reported twice.
+    #[structopt(long, parse(from_os_str))]
+    rejections: Option<std::path::PathBuf>,
+    /// Format of the rejected events report
+    #[structopt(
+        long,
+        default_value = "csv",
+        possible_values = &["csv", "ndjson"]
+    )]
+    rejections_format: ReportFormat,
+    /// Writes the ledger of every client to the given file, each applied event with the resulting
+    /// balances and the final state of its transaction. With `--state-in` or `--recover` the
+    /// ledger starts from the restored balances, the restored events are not listed.
+    #[structopt(long, parse(from_os_str))]
+    ledger: Option<std::path::PathBuf>,
+    /// Format of the ledger
+    #[structopt(
+        long,
+        default_value = "csv",
+        possible_values = &["csv", "ndjson"]
+    )]
+    ledger_format: ReportFormat,
+    /// Treatment of a dispute exceeding the available amount: reject it (forbid), let the
+    /// available amount go negative (allow) or hold only the available amount (cap)
+    #[structopt(
+        long,
+        default_value = "allow",
+        possible_values = &["forbid", "allow", "cap"]
+    )]
+    negative_balance: NegativeBalance,
+    /// Treatment of a fee exceeding the available amount: reject it (forbid), let the available
+    /// amount go negative (allow) or charge only the available amount (cap)
+    #[structopt(
+        long,
+        default_value = "allow",
+        possible_values = &["forbid", "allow", "cap"]
+    )]
+    negative_fee: NegativeBalance,
+    /// Comma separated list of the event types still accepted by a locked account, e.g.
+    /// `deposit,dispute,resolve`
+    #[structopt(long, default_value = "none")]
+    locked_allow: LockedPolicy,
+    /// Number of worker threads handling the clients, by default the number of CPUs
+    #[structopt(long)]
+    workers: Option<usize>,
+    /// Number of events queued for a worker before the reader waits for it, by default 1024
+    #[structopt(long)]
+    queue_capacity: Option<usize>,
+    /// Writes the result to the given file instead of stdout
+    #[structopt(long, parse(from_os_str))]
+    output: Option<std::path::PathBuf>,
+    /// Writes the clients sorted by their id
+    #[structopt(long)]
+    sorted: bool,
+    /// Format of the result
+    #[structopt(
+        long,
+        default_value = "csv",
+        possible_values = &["csv", "json", "ndjson", "table"]
+    )]
+    format: OutputFormat,
+    /// Starts from the state written by an earlier run with `--state-out`, instead of zero balances
+    #[structopt(long, parse(from_os_str))]
+    state_in: Option<std::path::PathBuf>,
+    /// Writes the full state of the engine to the given file, so a later run could continue from
+    /// it with `--state-in`
+    #[structopt(long, parse(from_os_str))]
+    state_out: Option<std::path::PathBuf>,
+    /// Journals the submitted events with periodic checkpoints to the given directory, so a run
+    /// died halfway could be continued with `--recover`
+    #[structopt(long, parse(from_os_str))]
+    journal: Option<std::path::PathBuf>,
+    /// Minimal number of journaled events between two checkpoints, a checkpoint also waits until
+    /// the journal is as large as the previous checkpoint
+    #[structopt(long, default_value = "100000")]
+    checkpoint_interval: u64,
+    /// Recovers the state from the journal, and resumes reading the same inputs after the last
+    /// journaled row. `--state-in` is ignored, the checkpoints already include it.
+    #[structopt(long, requires = "journal")]
+    recover: bool,
+    /// Prints the time spent waiting for the workers to stderr
+    #[structopt(long)]
+    stats: bool,
+    /// Format of the error printed to stderr, if the processing fails
+    #[structopt(
+        long,
+        default_value = "text",
+        possible_values = &["text", "json"]
+    )]
+    error_format: ErrorFormat,
 }
 
-/// Main entry point. Requires an input CSV file and the result is dumped to stdout.
+/// Main entry point. Requires one or more input CSV files and the result is dumped to stdout.
+/// If the processing fails, the error is printed to stderr, and the exit code tells the kind of
+/// the error, see `KrctError::exit_code`.
 ///
 /// # Example
 /// ```bash
 /// $ cargo run --release -- input.csv > output.csv
+/// $ cat input.csv | cargo run --release -- - partitions/ > output.csv
 /// ```
 #[paw::main]
 fn main(args: Args) {
-    match Krct::try_from(args.input) {
-        Ok(krct) => {
-            if let Err(err) = krct.dump(std::io::stdout()) {
-                eprint!("{}", err)
+    let error_format = args.error_format;
+    if let Err(err) = run(args) {
+        report(&err, error_format);
+        std::process::exit(err.exit_code());
+    }
+}
+
+/// Prints the error to stderr in the given format
+fn report(err: &KrctError, format: ErrorFormat) {
+    match format {
+        ErrorFormat::Text => eprintln!("{}", err),
+        ErrorFormat::Json => {
+            let mut report = serde_json::json!({
+                "error": err.kind(),
+                "code": err.exit_code(),
+                "message": err.to_string(),
+            });
+            match err {
+                KrctError::Worker(client) => report["clients"] = serde_json::json!([client]),
+                KrctError::Failed(clients) => report["clients"] = serde_json::json!(clients),
+                KrctError::MalformedRow(row) => {
+                    report["input"] = serde_json::json!(row.input);
+                    report["line"] = serde_json::json!(row.line);
+                }
+                _ => (),
+            }
+            eprintln!("{}", report);
+        }
+    }
+}
+
+fn run(args: Args) -> krct::Result<()> {
+    let config = Config {
+        strictness: args.strict,
+        rejections: args.rejections.is_some(),
+        ledger: args.ledger.is_some(),
+        policy: Policy {
+            negative_balance: args.negative_balance,
+            locked: args.locked_allow,
+            fee: args.negative_fee,
+        },
+        workers: args.workers,
+        queue_capacity: args.queue_capacity,
+    };
+    let paths = expand(args.inputs)?;
+    let mut engine = match (args.journal, args.state_in) {
+        (Some(journal), _) if args.recover => {
+            Engine::recover(config, journal, args.checkpoint_interval)?
+        }
+        (journal, state_in) => {
+            let engine = match state_in {
+                Some(path) => {
+                    let state = State::read(open(&path)?)?;
+                    Engine::from_state(config, state)?
+                }
+                None => Engine::new(config),
+            };
+            match journal {
+                Some(journal) => {
+                    engine.with_journal(Journal::create(journal, args.checkpoint_interval)?)?
+                }
+                None => engine,
             }
         }
-        Err(err) => eprint!("{}", err),
+    };
+    // The rejections are written while the input is read, so they are not piled up in memory
+    let reporter = match (engine.rejections(), args.rejections) {
+        (Some(rejections), Some(path)) => {
+            // A recovered run replays only the events after the latest checkpoint, so the report
+            // of the died run is continued instead of truncated
+            let file = std::fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(args.recover)
+                .truncate(!args.recover)
+                .open(path)
+                .map_err(KrctError::CsvDump)?;
+            let append = file.metadata().map_err(KrctError::CsvDump)?.len() > 0;
+            let writer = std::io::BufWriter::new(file);
+            let format = args.rejections_format;
+            Some(std::thread::spawn(move || match append {
+                true => rejections.append(writer, format),
+                false => rejections.dump(writer, format),
+            }))
+        }
+        _ => None,
+    };
+    // An input is opened only when it is reached, so a directory of many partitions does not
+    // hold a file descriptor for each of them
+    for path in &paths {
+        engine.submit_all([open(path)?])?;
+    }
+    let krct = engine.finish();
+    for row in krct.rejected_rows() {
+        match paths.as_slice() {
+            [_] => eprintln!("Rejected row {}", row),
+            paths => eprintln!("Rejected row in {} {}", paths[row.input].display(), row),
+        }
+    }
+    if args.stats {
+        eprintln!("Blocked on backpressure: {:?}", krct.blocked());
+    }
+
+    if let Some(path) = args.state_out {
+        save(&path, &krct.state()?)?;
+    }
+    if let Some(path) = args.ledger {
+        let writer =
+            std::io::BufWriter::new(std::fs::File::create(path).map_err(KrctError::CsvDump)?);
+        krct.ledger()?.dump(writer, args.ledger_format)?;
+    }
+
+    let output: Box<dyn std::io::Write> = match args.output {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path).map_err(KrctError::CsvDump)?,
+        )),
+        None => Box::new(std::io::stdout()),
+    };
+    if args.sorted {
+        krct.dump_sorted_as(output, args.format)?;
+    } else {
+        krct.dump_as(output, args.format)?;
+    }
+    // The stream of the rejections ends, when the engine has been dumped
+    match reporter.map(std::thread::JoinHandle::join) {
+        Some(Ok(result)) => result,
+        Some(Err(_)) => Err(KrctError::CsvDump(std::io::Error::other(
+            "Rejection report could not be written",
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Expands the given input paths, a directory is replaced by its files sorted by name
+fn expand(paths: Vec<std::path::PathBuf>) -> krct::Result<Vec<std::path::PathBuf>> {
+    let mut inputs = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut files = std::fs::read_dir(&path)
+                .and_then(|entries| {
+                    entries
+                        .map(|entry| entry.map(|entry| entry.path()))
+                        .collect::<std::io::Result<Vec<_>>>()
+                })
+                .map_err(|error| KrctError::Input { path, error })?;
+            files.retain(|file| file.is_file());
+            files.sort();
+            inputs.extend(files);
+        } else {
+            inputs.push(path);
+        }
+    }
+
+    Ok(inputs)
+}
+
+/// Writes the state to the given path. The state is written to a temporary file in the same
+/// directory first, and it is renamed over the target, so a failed run never leaves a truncated
+/// state behind.
+fn save(path: &std::path::Path, state: &State) -> krct::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = std::path::PathBuf::from(temporary);
+    let file = std::fs::File::create(&temporary).map_err(KrctError::StateWrite)?;
+    state.write(&file)?;
+    file.sync_all().map_err(KrctError::StateWrite)?;
+    std::fs::rename(&temporary, path).map_err(KrctError::StateWrite)
+}
+
+/// Opens an input path, `-` is the stdin
+fn open(path: &std::path::PathBuf) -> krct::Result<Box<dyn std::io::Read>> {
+    if path.as_os_str() == "-" {
+        return Ok(Box::new(std::io::stdin()));
+    }
+
+    match std::fs::File::open(path) {
+        Ok(file) => Ok(Box::new(file)),
+        Err(error) => Err(KrctError::Input {
+            path: path.clone(),
+            error,
+        }),
     }
 }
@@ -0,0 +1,176 @@
+use crate::config::OutputFormat;
+use std::io::Write;
+
+/// Writer of serializable rows, e.g. the client accounts, in the given output format. The rows
+/// are written as they arrive, except the table, which needs all the rows to align the columns.
+pub(crate) enum Output<W: Write> {
+    Csv(csv::Writer<W>),
+    Json {
+        writer: W,
+        rows: usize,
+    },
+    Ndjson(W),
+    Table {
+        writer: W,
+        rows: csv::Writer<Vec<u8>>,
+    },
+}
+
+impl<W: Write> Output<W> {
+    /// Constructing a new output to the given writer in the given format
+    pub(crate) fn new(writer: W, format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Csv => Output::Csv(csv::Writer::from_writer(writer)),
+            OutputFormat::Json => Output::Json { writer, rows: 0 },
+            OutputFormat::Ndjson => Output::Ndjson(writer),
+            OutputFormat::Table => Output::Table {
+                writer,
+                rows: csv::Writer::from_writer(Vec::new()),
+            },
+        }
+    }
+
+    /// Writes a single row
+    pub(crate) fn write<T: serde::Serialize>(&mut self, row: T) -> std::io::Result<()> {
+        match self {
+            Output::Csv(writer) => {
+                writer.serialize(row)?;
+                writer.flush()?;
+            }
+            Output::Json { writer, rows } => {
+                writer.write_all(if *rows == 0 { b"[" } else { b"," })?;
+                serde_json::to_writer(&mut *writer, &row)?;
+                *rows += 1;
+            }
+            Output::Ndjson(writer) => {
+                serde_json::to_writer(&mut *writer, &row)?;
+                writeln!(writer)?;
+            }
+            Output::Table { rows, .. } => rows.serialize(row)?,
+        }
+
+        Ok(())
+    }
+
+    /// Closes the output, e.g. the JSON array, and writes the table
+    pub(crate) fn finish(self) -> std::io::Result<()> {
+        match self {
+            Output::Csv(mut writer) => writer.flush()?,
+            Output::Json { mut writer, rows } => {
+                writer.write_all(if rows == 0 { b"[]\n" } else { b"]\n" })?;
+                writer.flush()?;
+            }
+            Output::Ndjson(mut writer) => writer.flush()?,
+            Output::Table { mut writer, rows } => {
+                let rows = rows.into_inner().map_err(|err| err.into_error())?;
+                Self::table(&mut writer, &rows)?;
+                writer.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the given CSV rows as a table, each column is padded to its widest value
+    fn table(writer: &mut W, rows: &[u8]) -> std::io::Result<()> {
+        let rows = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(rows)
+            .into_records()
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut widths = Vec::new();
+        for row in &rows {
+            widths.resize(widths.len().max(row.len()), 0);
+            for (width, field) in widths.iter_mut().zip(row) {
+                *width = (*width).max(field.chars().count());
+            }
+        }
+
+        for row in &rows {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .map(|(field, width)| format!("{:width$}", field, width = width))
+                .collect::<Vec<_>>()
+                .join("  ");
+            writeln!(writer, "{}", line.trim_end())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Output;
+    use crate::config::OutputFormat;
+
+    #[derive(serde::Serialize)]
+    struct Row {
+        client: u16,
+        available: &'static str,
+        locked: bool,
+    }
+
+    fn output(format: OutputFormat) -> String {
+        let mut buffer = Vec::new();
+        let mut output = Output::new(&mut buffer, format);
+        for (client, available) in [(1, "1.5"), (12, "-10.25")] {
+            output
+                .write(Row {
+                    client,
+                    available,
+                    locked: false,
+                })
+                .expect("Failed to write row");
+        }
+        output.finish().expect("Failed to finish output");
+
+        String::from_utf8(buffer).expect("Output should be UTF-8")
+    }
+
+    #[test]
+    fn test_csv_output() {
+        assert_eq!(
+            output(OutputFormat::Csv),
+            "client,available,locked\n1,1.5,false\n12,-10.25,false\n"
+        );
+    }
+
+    #[test]
+    fn test_json_output() {
+        assert_eq!(
+            output(OutputFormat::Json),
+            "[{\"client\":1,\"available\":\"1.5\",\"locked\":false},\
+            {\"client\":12,\"available\":\"-10.25\",\"locked\":false}]\n"
+        );
+    }
+
+    #[test]
+    fn test_ndjson_output() {
+        assert_eq!(
+            output(OutputFormat::Ndjson),
+            "{\"client\":1,\"available\":\"1.5\",\"locked\":false}\n\
+            {\"client\":12,\"available\":\"-10.25\",\"locked\":false}\n"
+        );
+    }
+
+    #[test]
+    fn test_table_output() {
+        assert_eq!(
+            output(OutputFormat::Table),
+            "client  available  locked\n\
+            1       1.5        false\n\
+            12      -10.25     false\n"
+        );
+    }
+
+    #[test]
+    fn test_empty_json_output() {
+        let mut buffer = Vec::new();
+        assert!(Output::new(&mut buffer, OutputFormat::Json)
+            .finish()
+            .is_ok());
+        assert_eq!(buffer, b"[]\n");
+    }
+}
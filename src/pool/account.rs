@@ -0,0 +1,73 @@
+use super::amount::Amount;
+use super::client::{Client, LockReason};
+
+/// Point-in-time view of the balances of a client account
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Account {
+    #[serde(rename = "client")]
+    id: u16,
+    available: Amount,
+    held: Amount,
+    total: Amount,
+    locked: bool,
+    lock_reason: Option<LockReason>,
+    fees: Amount,
+    interest: Amount,
+}
+
+impl Account {
+    /// Client ID of the account
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// Amount available for withdrawal
+    pub fn available(&self) -> Amount {
+        self.available
+    }
+
+    /// Amount held by disputes
+    pub fn held(&self) -> Amount {
+        self.held
+    }
+
+    /// Total amount, the sum of the available and the held amounts
+    pub fn total(&self) -> Amount {
+        self.total
+    }
+
+    /// Whether the account is locked
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Reason of the lock, if the account is locked
+    pub fn lock_reason(&self) -> Option<LockReason> {
+        self.lock_reason
+    }
+
+    /// Total of the fees charged to the account
+    pub fn fees(&self) -> Amount {
+        self.fees
+    }
+
+    /// Total of the interest credited to the account
+    pub fn interest(&self) -> Amount {
+        self.interest
+    }
+}
+
+impl From<&Client> for Account {
+    fn from(client: &Client) -> Self {
+        Self {
+            id: client.id,
+            available: client.available,
+            held: client.held,
+            total: client.total,
+            locked: client.locked,
+            lock_reason: client.lock_reason,
+            fees: client.fees,
+            interest: client.interest,
+        }
+    }
+}
@@ -1,95 +1,263 @@
-/// Utility to ease handling amount values in events
-#[derive(Debug, serde::Serialize)]
-pub(in crate::pool) struct Amount<T>(pub T);
+use crate::error::AmountError;
 
-impl std::ops::AddAssign<f32> for Amount<f64> {
-    fn add_assign(&mut self, rhs: f32) {
-        self.0 += rhs as f64
+/// Number of fractional digits an amount is stored with
+const PRECISION: usize = 4;
+/// Scaling factor between the stored integer value and one unit
+const SCALE: i64 = 10_i64.pow(PRECISION as u32);
+
+/// Exact fixed-point decimal amount with four fractional digits. The value is stored as an
+/// integer count of ten-thousandths, so adding and subtracting amounts never drifts. All
+/// arithmetic is checked, an overflow is reported instead of being wrapped or rounded.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(i64);
+
+impl Amount {
+    /// Zero amount
+    pub const ZERO: Amount = Amount(0);
+
+    /// Constructing an amount from its signed number of ten-thousandths, e.g. `from_minor(-5000)`
+    /// is -0.5
+    pub const fn from_minor(minor: i64) -> Amount {
+        Amount(minor)
+    }
+
+    /// Signed number of ten-thousandths of the amount, see `Amount::from_minor`
+    pub const fn minor(self) -> i64 {
+        self.0
+    }
+
+    /// Constructing an amount from its integer part and its fraction in ten-thousandths, the
+    /// fraction has the sign of the integer part, e.g. `from_parts(-1, 5000)` is -1.5. An amount
+    /// between -1 and 0 has no signed integer part, it is constructed by `Amount::from_minor`.
+    ///
+    /// Returns `None`, if the fraction is not below 10000, or the amount is out of range.
+    pub fn from_parts(integer: i64, fraction: u16) -> Option<Amount> {
+        if i64::from(fraction) >= SCALE {
+            return None;
+        }
+        let fraction = if integer < 0 {
+            -i64::from(fraction)
+        } else {
+            i64::from(fraction)
+        };
+
+        integer
+            .checked_mul(SCALE)
+            .and_then(|scaled| scaled.checked_add(fraction))
+            .map(Amount)
+    }
+
+    /// Adds two amounts, returning `None` upon overflow
+    pub fn checked_add(self, rhs: Amount) -> Option<Amount> {
+        self.0.checked_add(rhs.0).map(Amount)
+    }
+
+    /// Subtracts two amounts, returning `None` upon overflow
+    pub fn checked_sub(self, rhs: Amount) -> Option<Amount> {
+        self.0.checked_sub(rhs.0).map(Amount)
+    }
+
+    /// Negates the amount, returning `None` upon overflow
+    pub fn checked_neg(self) -> Option<Amount> {
+        self.0.checked_neg().map(Amount)
     }
 }
 
-impl std::ops::AddAssign<&Amount<f32>> for Amount<f64> {
-    fn add_assign(&mut self, rhs: &Amount<f32>) {
-        self.0 += rhs.0 as f64
+impl TryFrom<&str> for Amount {
+    type Error = AmountError;
+
+    /// Parses a decimal number, see `FromStr`
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
     }
 }
 
-impl std::ops::SubAssign<f32> for Amount<f64> {
-    fn sub_assign(&mut self, rhs: f32) {
-        if self.0 >= rhs as f64 {
-            self.0 -= rhs as f64
+impl std::str::FromStr for Amount {
+    type Err = AmountError;
+
+    /// Parses a decimal number with at most four fractional digits, e.g. `1`, `-0.5`, `2.1234`.
+    /// Values with more fractional digits are refused, since they cannot be represented exactly.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || AmountError::Invalid(value.to_string());
+        let (negative, unsigned) = match value.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, value.strip_prefix('+').unwrap_or(value)),
+        };
+        let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(invalid());
+        }
+        if !integer
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        if fraction.len() > PRECISION {
+            return Err(AmountError::Precision(value.to_string()));
         }
+
+        let integer = match integer {
+            "" => 0,
+            digits => digits
+                .parse::<i64>()
+                .map_err(|_| AmountError::Overflow(value.to_string()))?,
+        };
+        let fraction = format!("{:0<width$}", fraction, width = PRECISION)
+            .parse::<i64>()
+            .map_err(|_| invalid())?;
+        let scaled = integer
+            .checked_mul(SCALE)
+            .and_then(|scaled| scaled.checked_add(fraction))
+            .ok_or_else(|| AmountError::Overflow(value.to_string()))?;
+
+        Ok(Amount(if negative { -scaled } else { scaled }))
     }
 }
 
-impl std::ops::SubAssign<&Amount<f32>> for Amount<f64> {
-    fn sub_assign(&mut self, rhs: &Amount<f32>) {
-        self.0 -= rhs.0 as f64
+impl std::fmt::Display for Amount {
+    /// Formats the amount with its significant fractional digits, but at least one, e.g. `1.0`,
+    /// `1.5`, `0.0001`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let value = self.0.unsigned_abs();
+        let scale = SCALE as u64;
+        let fraction = format!("{:0width$}", value % scale, width = PRECISION);
+        let fraction = match fraction.trim_end_matches('0') {
+            "" => "0",
+            trimmed => trimmed,
+        };
+
+        write!(f, "{}{}.{}", sign, value / scale, fraction)
     }
 }
 
-impl PartialOrd<f32> for Amount<f64> {
-    fn partial_cmp(&self, other: &f32) -> Option<std::cmp::Ordering> {
-        self.0.partial_cmp(&(*other as f64))
+impl serde::Serialize for Amount {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
     }
 }
 
-impl PartialEq<f32> for Amount<f64> {
-    fn eq(&self, other: &f32) -> bool {
-        self.0 == (*other) as f64
+impl<'de> serde::Deserialize<'de> for Amount {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
     }
 }
 
 #[cfg(test)]
-impl<T> PartialEq<Amount<T>> for Amount<T>
-where
-    T: PartialEq,
-{
-    fn eq(&self, other: &Amount<T>) -> bool {
-        self.0 == other.0
+impl From<f64> for Amount {
+    fn from(val: f64) -> Self {
+        Amount((val * SCALE as f64).round() as i64)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::Amount;
+    use crate::error::AmountError;
 
     #[test]
     fn test_increase() {
-        let mut amount = Amount(1.0);
-        amount += 1.5;
-        assert_eq!(amount.0, 2.5);
+        let amount = Amount::from(1.0).checked_add(Amount::from(1.5));
+        assert_eq!(amount, Some(Amount::from(2.5)));
     }
 
     #[test]
     fn test_decrement() {
-        let mut amount = Amount(1.5);
-        amount -= 0.5;
-        assert_eq!(amount.0, 1.0);
+        let amount = Amount::from(1.5).checked_sub(Amount::from(0.5));
+        assert_eq!(amount, Some(Amount::from(1.0)));
+    }
+
+    #[test]
+    fn test_no_drift() {
+        let amount = "0.1"
+            .parse::<Amount>()
+            .unwrap()
+            .checked_add("0.2".parse().unwrap());
+        assert_eq!(amount, Some("0.3".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_overflow_is_rejected() {
+        assert_eq!(Amount(i64::MAX).checked_add(Amount(1)), None);
+        assert_eq!(Amount(i64::MIN).checked_sub(Amount(1)), None);
     }
 
     #[test]
-    fn test_cannot_decrement_under_zero() {
-        let mut amount = Amount(1.5);
-        amount -= 2.0;
-        assert_eq!(amount.0, 1.5);
+    fn test_negate() {
+        assert_eq!(Amount::from(1.5).checked_neg(), Some(Amount::from(-1.5)));
+        assert_eq!(Amount(i64::MIN).checked_neg(), None);
     }
 
     #[test]
     fn test_lesser() {
-        let amount = Amount(1.5);
-        assert!(amount < 2.0);
+        assert!(Amount::from(1.5) < Amount::from(2.0));
     }
 
     #[test]
     fn test_greater() {
-        let amount = Amount(1.5);
-        assert!(amount > 1.0);
+        assert!(Amount::from(1.5) > Amount::from(1.0));
+    }
+
+    #[test]
+    fn test_parsing() {
+        assert_eq!("1".parse(), Ok(Amount(10_000)));
+        assert_eq!("1.5".parse(), Ok(Amount(15_000)));
+        assert_eq!(".5".parse(), Ok(Amount(5_000)));
+        assert_eq!("-0.0001".parse(), Ok(Amount(-1)));
+        assert_eq!("2.1234".parse(), Ok(Amount(21_234)));
+    }
+
+    #[test]
+    fn test_parsing_errors() {
+        assert!(matches!(
+            "a".parse::<Amount>(),
+            Err(AmountError::Invalid(_))
+        ));
+        assert!(matches!("".parse::<Amount>(), Err(AmountError::Invalid(_))));
+        assert!(matches!(
+            "1.2.3".parse::<Amount>(),
+            Err(AmountError::Invalid(_))
+        ));
+        assert!(matches!(
+            "1.00001".parse::<Amount>(),
+            Err(AmountError::Precision(_))
+        ));
+        assert!(matches!(
+            "99999999999999999999".parse::<Amount>(),
+            Err(AmountError::Overflow(_))
+        ));
+    }
+
+    #[test]
+    fn test_constructing_from_parts() {
+        assert_eq!(Amount::from_parts(1, 5000), Some(Amount(15_000)));
+        assert_eq!(Amount::from_parts(-1, 5000), Some(Amount(-15_000)));
+        assert_eq!(Amount::from_parts(0, 1), Some(Amount(1)));
+        assert_eq!(Amount::from_parts(1, 10_000), None);
+        assert_eq!(Amount::from_parts(i64::MAX, 0), None);
+        assert_eq!(Amount::from_minor(-5000), Amount(-5_000));
+        assert_eq!(Amount::from_minor(-5000).to_string(), "-0.5");
+        assert_eq!(
+            Amount::from_minor(-15_000),
+            Amount::from_parts(-1, 5000).unwrap()
+        );
+        assert_eq!(Amount::from_minor(-5000).minor(), -5000);
+        assert_eq!(Amount::try_from("1.5"), Ok(Amount(15_000)));
+        assert!(matches!(
+            Amount::try_from("1.00001"),
+            Err(AmountError::Precision(_))
+        ));
     }
 
     #[test]
-    fn test_equals() {
-        let amount = Amount(1.5);
-        assert_eq!(amount, 1.5);
+    fn test_formatting() {
+        assert_eq!(Amount::from(1.0).to_string(), "1.0");
+        assert_eq!(Amount::from(1.5).to_string(), "1.5");
+        assert_eq!(Amount(1).to_string(), "0.0001");
+        assert_eq!(Amount(-5_000).to_string(), "-0.5");
+        assert_eq!(Amount::ZERO.to_string(), "0.0");
     }
 }
@@ -1,129 +1,393 @@
 use super::amount::Amount;
-use super::history::{History, State};
+use super::history::{Direction, History, Record};
+use super::ledger::Step;
+use super::rejection::{Reason, Rejection};
+use crate::config::Policy;
 use crate::pool::Event;
+use crate::TransactionType;
 use std::sync::mpsc;
 
+/// Reason of a client account being locked
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockReason {
+    /// A disputed deposit was charged back
+    Chargeback,
+    /// The account was frozen by a lock event
+    Manual,
+}
+
+/// State of a client before a leg is applied, to be able to roll it back
+#[derive(Debug)]
+struct Backup {
+    tx: u32,
+    record: Option<Record>,
+    available: Amount,
+    held: Amount,
+    total: Amount,
+    locked: bool,
+    lock_reason: Option<LockReason>,
+    ledger: usize,
+}
+
 /// Main business logic, handling events corresponding to the given client.
 #[derive(Debug, serde::Serialize)]
 pub struct Client {
     #[serde(rename = "client")]
     pub(in crate::pool) id: u16,
     #[serde(skip_serializing)]
-    pub(in crate::pool) transaction_history: History<u32, f32>,
-    pub(in crate::pool) available: Amount<f64>,
-    pub(in crate::pool) held: Amount<f64>,
-    pub(in crate::pool) total: Amount<f64>,
+    pub(in crate::pool) transaction_history: History<u32>,
+    pub(in crate::pool) available: Amount,
+    pub(in crate::pool) held: Amount,
+    pub(in crate::pool) total: Amount,
     pub(in crate::pool) locked: bool,
+    pub(in crate::pool) lock_reason: Option<LockReason>,
+    pub(in crate::pool) fees: Amount,
+    pub(in crate::pool) interest: Amount,
 
+    /// The applied events, if the ledger is enabled
+    #[serde(skip_serializing)]
+    pub(in crate::pool) ledger: Option<Vec<Step>>,
+    #[serde(skip_serializing)]
+    pending: Option<Backup>,
+    #[serde(skip_serializing)]
+    rejections: Option<mpsc::Sender<Rejection>>,
     #[serde(skip_serializing)]
-    channel: mpsc::Receiver<Event>,
+    policy: Policy,
 }
 
 impl Client {
-    /// Constructing a new client with the given Client ID and the receiver part of the
-    /// communication channel
-    pub fn new(client_id: u16, channel: mpsc::Receiver<Event>) -> Self {
+    /// Constructing a new client with the given Client ID. Rejected events are reported to the
+    /// optional rejection channel, and the events are handled according to the given policy. The
+    /// applied events are recorded, if the ledger is enabled.
+    pub fn new(
+        client_id: u16,
+        rejections: Option<mpsc::Sender<Rejection>>,
+        policy: Policy,
+        ledger: bool,
+    ) -> Self {
         Self {
             id: client_id,
-            channel,
+            ledger: ledger.then(Vec::new),
+            pending: None,
+            rejections,
+            policy,
 
             transaction_history: History::default(),
-            available: Amount(0.0),
-            held: Amount(0.0),
-            total: Amount(0.0),
+            available: Amount::ZERO,
+            held: Amount::ZERO,
+            total: Amount::ZERO,
             locked: false,
+            lock_reason: None,
+            fees: Amount::ZERO,
+            interest: Amount::ZERO,
+        }
+    }
+
+    /// Handles an event, and if it is rejected, then it is reported with the reason
+    pub(in crate::pool) fn handle_event(&mut self, event: &Event) {
+        match self.handle(event) {
+            Ok(amount) => self.record(event, None, amount),
+            Err(reason) => self.reject(event, reason),
+        }
+    }
+
+    /// Handles a leg of an event affecting more clients. The leg is handled as a standalone event,
+    /// but an applied leg is pending until the decision of the pool arrives. A rejected leg is not
+    /// reported by the client, the pool reports the whole event instead. The counterparty is the
+    /// client of the other leg.
+    pub(in crate::pool) fn handle_leg(
+        &mut self,
+        event: &Event,
+        counterparty: u16,
+        reply: mpsc::Sender<Result<Amount, Reason>>,
+    ) {
+        let backup = self.backup(event.tx());
+        let result = self.handle(event);
+        if let Ok(amount) = result {
+            self.record(event, Some(counterparty), amount);
+            self.pending = Some(backup);
+        }
+        // The pool is gone only if the processing is aborted
+        let _ = reply.send(result);
+    }
+
+    /// Commits or rolls back the pending leg
+    pub(in crate::pool) fn decide(&mut self, commit: bool) {
+        if let Some(backup) = self.pending.take() {
+            if !commit {
+                self.restore(backup);
+            }
         }
     }
 
-    /// Event handling thread, receiving events from the sender via the previously given channel.
+    /// Handles a single event.
     ///
     /// # Events
-    /// * Deposit: increase the available and total amount
-    /// * Withdrawal: decreasing the available and total amount
-    /// * Dispute: decreasing the available, but not the talal. Also, increasing the held amount
-    /// * Resolve: a previously disputed transaction resolved and the available amount should be increased
-    /// * Chargeback: a previously disputed transaction should be charged back. The total and the available
-    ///   amount should be decreased and the client has to be locked.
+    /// Dispute, resolve and chargeback could have an amount to affect only a part of the referenced
+    /// transaction, without it the whole disputable or disputed amount is affected.
     ///
-    /// # Finish
-    /// Special event to indicate the processing of the events should be finished and the handling
-    /// thread has to be stopped
-    pub fn start_handling(mut self) -> Self {
-        while let Ok(event) = self.channel.recv() {
-            match event {
-                Event::Finish => break,
-                Event::Deposit { amount, tx, .. } if !self.locked => {
-                    self.transaction_history.insert(tx, Amount(amount));
-                    self.available += amount;
-                    self.total += amount;
-                }
-                Event::Withdrawal { amount, .. } if !self.locked && self.available >= amount => {
-                    self.available -= amount;
-                    self.total -= amount;
+    /// * Deposit: increase the available and total amount, the amount has to be positive
+    /// * Withdrawal: decreasing the available and total amount, the amount has to be positive
+    /// * Dispute: a previously recorded transaction is disputed and its amount is held
+    ///   - deposit: decreasing the available, but not the total. Also, increasing the held amount.
+    ///     If the available amount does not cover the dispute, the negative balance policy decides
+    ///   - withdrawal: the amount is a pending credit, increasing the held and the total amount
+    /// * Resolve: a previously disputed transaction resolved, the dispute is cancelled
+    ///   - deposit: the held amount is released, the available amount should be increased
+    ///   - withdrawal: the pending credit is cancelled, the held and the total amount is decreased
+    /// * Chargeback: a previously disputed transaction should be charged back
+    ///   - deposit: the total and the held amount should be decreased and the client has to be
+    ///     locked
+    ///   - withdrawal: the pending credit is returned to the client, the held amount is released
+    ///     and the available amount is increased
+    /// * Fee: decreasing the available and total amount, and increasing the total of the fees. If
+    ///   the available amount does not cover the fee, the fee policy decides. A fee is not
+    ///   disputable, and its amount has to be positive.
+    /// * Interest: increasing the available and total amount, and the total of the interest. An
+    ///   interest is not disputable, and its amount has to be positive.
+    /// * Lock: the client account is frozen manually
+    /// * Unlock: the client account is reactivated, regardless it was locked by a chargeback or
+    ///   manually
+    ///
+    /// A locked account accepts only the event types allowed by the locked policy. A transfer is
+    /// split into a withdrawal and a deposit by the pool, so it never reaches the client.
+    ///
+    /// Returns the amount affected by the event, e.g. the disputed amount of a dispute.
+    ///
+    /// # Error
+    /// The reason, if the event is rejected. A rejected event does not change the client at all.
+    fn handle(&mut self, event: &Event) -> Result<Amount, Reason> {
+        if self.locked && !self.policy.locked.accepts(event) {
+            return Err(Reason::AccountLocked);
+        }
+
+        let amount = match *event {
+            Event::Deposit { amount, tx, .. } => {
+                let amount = Self::positive(amount)?;
+                self.adjust(amount, Amount::ZERO, amount)?;
+                self.transaction_history
+                    .insert(tx, amount, Direction::Incoming);
+                amount
+            }
+            Event::Withdrawal { amount, tx, .. } => {
+                let amount = Self::positive(amount)?;
+                if self.available < amount {
+                    return Err(Reason::InsufficientFunds);
                 }
-                Event::Dispute { tx, .. } if !self.locked => {
-                    if let Some(amount) = self.transaction_history.select(tx, State::Recorded) {
-                        self.available -= amount;
-                        self.held += amount;
-                        self.transaction_history.set_state(tx, State::Held);
+                let debit = amount.checked_neg().ok_or(Reason::Overflow)?;
+                self.adjust(debit, Amount::ZERO, debit)?;
+                self.transaction_history
+                    .insert(tx, amount, Direction::Outgoing);
+                amount
+            }
+            Event::Dispute { tx, amount, .. } => {
+                let record = self.transaction_history.get(&tx)?;
+                let direction = record.direction;
+                let amount = Record::take(record.disputable(), amount)?;
+                let amount = match direction {
+                    Direction::Incoming => {
+                        let negative_balance = self.policy.negative_balance;
+                        let amount = negative_balance.limit(self.available, amount)?;
+                        let debit = amount.checked_neg().ok_or(Reason::Overflow)?;
+                        self.adjust(debit, amount, Amount::ZERO)?;
+                        amount
                     }
-                }
-                Event::Resolve { tx, .. } if !self.locked => {
-                    if let Some(amount) = self.transaction_history.select(tx, State::Held) {
-                        self.held -= amount;
-                        self.available += amount;
-                        self.transaction_history.set_state(tx, State::Recorded);
+                    Direction::Outgoing => {
+                        self.adjust(Amount::ZERO, amount, amount)?;
+                        amount
                     }
+                };
+                self.transaction_history.hold(&tx, amount);
+                amount
+            }
+            Event::Resolve { tx, amount, .. } => {
+                let record = self.transaction_history.get(&tx)?;
+                let direction = record.direction;
+                let amount = Record::take(record.held, amount)?;
+                let debit = amount.checked_neg().ok_or(Reason::Overflow)?;
+                match direction {
+                    Direction::Incoming => self.adjust(amount, debit, Amount::ZERO)?,
+                    Direction::Outgoing => self.adjust(Amount::ZERO, debit, debit)?,
                 }
-                Event::Chargeback { tx, .. } if !self.locked => {
-                    if let Some(amount) = self.transaction_history.select(tx, State::Held) {
-                        self.held -= amount;
-                        self.total -= amount;
-                        self.locked = true;
-                        self.transaction_history.set_state(tx, State::ChargedBack);
+                self.transaction_history.release(&tx, amount);
+                amount
+            }
+            Event::Chargeback { tx, amount, .. } => {
+                let record = self.transaction_history.get(&tx)?;
+                let direction = record.direction;
+                let amount = Record::take(record.held, amount)?;
+                let debit = amount.checked_neg().ok_or(Reason::Overflow)?;
+                match direction {
+                    Direction::Incoming => {
+                        self.adjust(Amount::ZERO, debit, debit)?;
+                        self.lock(LockReason::Chargeback);
                     }
+                    Direction::Outgoing => self.adjust(amount, debit, Amount::ZERO)?,
                 }
-                _ => (),
+                self.transaction_history.reverse(&tx, amount);
+                amount
             }
+            Event::Fee { amount, .. } => {
+                let amount = Self::positive(amount)?;
+                let amount = self.policy.fee.limit(self.available, amount)?;
+                let fees = self.fees.checked_add(amount).ok_or(Reason::Overflow)?;
+                let debit = amount.checked_neg().ok_or(Reason::Overflow)?;
+                self.adjust(debit, Amount::ZERO, debit)?;
+                self.fees = fees;
+                amount
+            }
+            Event::Interest { amount, .. } => {
+                let amount = Self::positive(amount)?;
+                let interest = self.interest.checked_add(amount).ok_or(Reason::Overflow)?;
+                self.adjust(amount, Amount::ZERO, amount)?;
+                self.interest = interest;
+                amount
+            }
+            Event::Lock { .. } => {
+                self.lock(LockReason::Manual);
+                Amount::ZERO
+            }
+            Event::Unlock { .. } if self.locked => {
+                self.locked = false;
+                self.lock_reason = None;
+                Amount::ZERO
+            }
+            Event::Unlock { .. } => return Err(Reason::InvalidState),
+            Event::Transfer { .. } => Amount::ZERO,
+        };
+
+        Ok(amount)
+    }
+
+    /// Records an applied event with the resulting balances, if the ledger is enabled. A withdrawal
+    /// or a deposit leg is a side of a transfer, so it is recorded as a transfer with its
+    /// direction and the client of the other side.
+    fn record(&mut self, event: &Event, counterparty: Option<u16>, amount: Amount) {
+        let (kind, transfer) = match (event, counterparty) {
+            (Event::Withdrawal { .. }, Some(other)) => (
+                TransactionType::Transfer,
+                Some((Direction::Outgoing, other)),
+            ),
+            (Event::Deposit { .. }, Some(other)) => (
+                TransactionType::Transfer,
+                Some((Direction::Incoming, other)),
+            ),
+            _ => (event.kind(), None),
+        };
+        if let Some(ledger) = &mut self.ledger {
+            ledger.push(Step {
+                tx: event.tx(),
+                kind,
+                transfer,
+                amount,
+                available: self.available,
+                held: self.held,
+                total: self.total,
+            });
         }
+    }
 
-        self
+    /// Validates the amount of a deposit, a withdrawal, a fee or an interest
+    ///
+    /// # Error
+    /// InvalidAmount: if the amount is not positive
+    fn positive(amount: Amount) -> Result<Amount, Reason> {
+        if amount > Amount::ZERO {
+            Ok(amount)
+        } else {
+            Err(Reason::InvalidAmount)
+        }
     }
-}
 
-#[cfg(test)]
-impl From<f32> for Amount<f32> {
-    fn from(val: f32) -> Self {
-        Amount(val)
+    /// Locks the client account with the given reason. An already locked account keeps the reason
+    /// of its first lock, e.g. a manual lock is not overwritten by a chargeback accepted by the
+    /// locked policy, so the reason shown is the one an unlock settles.
+    fn lock(&mut self, reason: LockReason) {
+        self.locked = true;
+        self.lock_reason.get_or_insert(reason);
     }
-}
 
-#[cfg(test)]
-impl From<f64> for Amount<f64> {
-    fn from(val: f64) -> Self {
-        Amount(val)
+    /// Saves the state, which could be changed by an event of the given transaction
+    fn backup(&self, tx: u32) -> Backup {
+        Backup {
+            tx,
+            record: self.transaction_history.backup(&tx),
+            available: self.available,
+            held: self.held,
+            total: self.total,
+            locked: self.locked,
+            lock_reason: self.lock_reason,
+            ledger: self.ledger.as_ref().map_or(0, Vec::len),
+        }
+    }
+
+    /// Restores a previously saved state
+    fn restore(&mut self, backup: Backup) {
+        self.transaction_history.restore(backup.tx, backup.record);
+        self.available = backup.available;
+        self.held = backup.held;
+        self.total = backup.total;
+        self.locked = backup.locked;
+        self.lock_reason = backup.lock_reason;
+        if let Some(ledger) = &mut self.ledger {
+            ledger.truncate(backup.ledger);
+        }
+    }
+
+    /// Changes the available, held and total amounts by the given signed amounts at once.
+    ///
+    /// # Error
+    /// If any of the amounts would overflow, in that case none of them is changed.
+    fn adjust(&mut self, available: Amount, held: Amount, total: Amount) -> Result<(), Reason> {
+        match (
+            self.available.checked_add(available),
+            self.held.checked_add(held),
+            self.total.checked_add(total),
+        ) {
+            (Some(available), Some(held), Some(total)) => {
+                self.available = available;
+                self.held = held;
+                self.total = total;
+                Ok(())
+            }
+            _ => Err(Reason::Overflow),
+        }
+    }
+
+    /// Reports a rejected event, if the rejections are collected
+    fn reject(&self, event: &Event, reason: Reason) {
+        if let Some(rejections) = &self.rejections {
+            // The receiver is gone only if nobody is interested in the rejections anymore
+            let _ = rejections.send(Rejection::new(event, reason));
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Amount, Client, History};
+    use super::{Amount, Client, Direction, History};
     use std::io::Read;
 
     #[test]
     fn test_client_serialization() {
-        let (_, rx) = std::sync::mpsc::channel();
         let mut tempfile = tempfile::NamedTempFile::new().expect("Failed to create testfile");
         let mut transaction_history = History::default();
-        transaction_history.insert(1, Amount(1.0));
+        transaction_history.insert(1, Amount::from(1.0), Direction::Incoming);
         let client = Client {
             id: 1,
             transaction_history,
-            available: Amount(10.0),
-            held: Amount(2.0),
-            total: Amount(12.0),
+            available: Amount::from(10.0),
+            held: Amount::from(2.0),
+            total: Amount::from(12.0),
             locked: false,
-            channel: rx,
+            lock_reason: None,
+            fees: Amount::from(0.5),
+            interest: Amount::ZERO,
+            ledger: None,
+            pending: None,
+            rejections: None,
+            policy: Default::default(),
         };
         let writer = csv::Writer::from_path(tempfile.as_ref());
         assert!(writer.is_ok(), "{}", writer.unwrap_err());
@@ -138,8 +402,8 @@ mod tests {
             .expect("Failed to read testfile");
         assert_eq!(
             buffer,
-            "client,available,held,total,locked\n\
-            1,10.0,2.0,12.0,false\n"
+            "client,available,held,total,locked,lock_reason,fees,interest\n\
+            1,10.0,2.0,12.0,false,,0.5,0.0\n"
         )
     }
 }
@@ -1,29 +1,185 @@
+use crate::error::KrctError;
+use crate::pool::Amount;
 use crate::{Transaction, TransactionType};
 
-/// Describing the given event and the field associated with the event
-#[derive(Debug)]
-#[cfg_attr(test, derive(PartialEq))]
+/// Describing the given event and the field associated with the event. More event types could be
+/// added later, so it is not matched exhaustively outside of the crate.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum Event {
     /// Transaction event for an incoming amount
-    Deposit { client: u16, tx: u32, amount: f32 },
+    Deposit {
+        client: u16,
+        tx: u32,
+        amount: Amount,
+    },
     /// Transaction event for an outgoing amount
-    Withdrawal { client: u16, tx: u32, amount: f32 },
-    /// Transaction event for a possible erroneous amount
-    Dispute { client: u16, tx: u32 },
-    /// Transaction event for an amount previously marked as erroneous is resolved
-    Resolve { client: u16, tx: u32 },
-    /// Transaction event for an amount previously marked as erroneous is charged back
-    Chargeback { client: u16, tx: u32 },
-
-    /// Special event, which indicates there will be no more data and the result set cloud be dumped
-    Finish,
-    /// Special event, which indicates the transaction type is not known by us
-    Unknown,
+    Withdrawal {
+        client: u16,
+        tx: u32,
+        amount: Amount,
+    },
+    /// Transaction event for a possible erroneous amount. Without an amount the whole remaining
+    /// disputable amount of the transaction is disputed.
+    Dispute {
+        client: u16,
+        tx: u32,
+        amount: Option<Amount>,
+    },
+    /// Transaction event for an amount previously marked as erroneous is resolved. Without an
+    /// amount the whole disputed amount is resolved.
+    Resolve {
+        client: u16,
+        tx: u32,
+        amount: Option<Amount>,
+    },
+    /// Transaction event for an amount previously marked as erroneous is charged back. Without an
+    /// amount the whole disputed amount is charged back.
+    Chargeback {
+        client: u16,
+        tx: u32,
+        amount: Option<Amount>,
+    },
+    /// Manual freeze of the client account
+    Lock { client: u16, tx: u32 },
+    /// Reactivation of a locked client account, regardless of the reason of the lock
+    Unlock { client: u16, tx: u32 },
+    /// Posting event charging a fee to the client
+    Fee {
+        client: u16,
+        tx: u32,
+        amount: Amount,
+    },
+    /// Posting event crediting interest to the client
+    Interest {
+        client: u16,
+        tx: u32,
+        amount: Amount,
+    },
+    /// Transaction event moving an amount from the client to the destination client
+    Transfer {
+        client: u16,
+        tx: u32,
+        destination: Option<u16>,
+        amount: Amount,
+    },
 }
 
-impl From<Transaction> for Event {
-    fn from(tx: Transaction) -> Self {
-        match tx._type {
+impl Event {
+    /// Deposit of the given amount to the client
+    pub fn deposit(client: u16, tx: u32, amount: Amount) -> Self {
+        Event::Deposit { client, tx, amount }
+    }
+
+    /// Withdrawal of the given amount from the client
+    pub fn withdrawal(client: u16, tx: u32, amount: Amount) -> Self {
+        Event::Withdrawal { client, tx, amount }
+    }
+
+    /// Dispute of the referenced transaction, without an amount the whole disputable amount
+    pub fn dispute(client: u16, tx: u32, amount: Option<Amount>) -> Self {
+        Event::Dispute { client, tx, amount }
+    }
+
+    /// Resolve of the referenced transaction, without an amount the whole disputed amount
+    pub fn resolve(client: u16, tx: u32, amount: Option<Amount>) -> Self {
+        Event::Resolve { client, tx, amount }
+    }
+
+    /// Chargeback of the referenced transaction, without an amount the whole disputed amount
+    pub fn chargeback(client: u16, tx: u32, amount: Option<Amount>) -> Self {
+        Event::Chargeback { client, tx, amount }
+    }
+
+    /// Manual freeze of the client account
+    pub fn lock(client: u16, tx: u32) -> Self {
+        Event::Lock { client, tx }
+    }
+
+    /// Reactivation of the client account
+    pub fn unlock(client: u16, tx: u32) -> Self {
+        Event::Unlock { client, tx }
+    }
+
+    /// Fee of the given amount charged to the client
+    pub fn fee(client: u16, tx: u32, amount: Amount) -> Self {
+        Event::Fee { client, tx, amount }
+    }
+
+    /// Interest of the given amount credited to the client
+    pub fn interest(client: u16, tx: u32, amount: Amount) -> Self {
+        Event::Interest { client, tx, amount }
+    }
+
+    /// Transfer of the given amount from the client to the destination client
+    pub fn transfer(client: u16, tx: u32, destination: u16, amount: Amount) -> Self {
+        Event::Transfer {
+            client,
+            tx,
+            destination: Some(destination),
+            amount,
+        }
+    }
+
+    /// Client ID of the event
+    pub fn client(&self) -> u16 {
+        match self {
+            Event::Deposit { client, .. }
+            | Event::Withdrawal { client, .. }
+            | Event::Dispute { client, .. }
+            | Event::Resolve { client, .. }
+            | Event::Chargeback { client, .. }
+            | Event::Lock { client, .. }
+            | Event::Unlock { client, .. }
+            | Event::Fee { client, .. }
+            | Event::Interest { client, .. }
+            | Event::Transfer { client, .. } => *client,
+        }
+    }
+
+    /// Transaction ID of the event
+    pub fn tx(&self) -> u32 {
+        match self {
+            Event::Deposit { tx, .. }
+            | Event::Withdrawal { tx, .. }
+            | Event::Dispute { tx, .. }
+            | Event::Resolve { tx, .. }
+            | Event::Chargeback { tx, .. }
+            | Event::Lock { tx, .. }
+            | Event::Unlock { tx, .. }
+            | Event::Fee { tx, .. }
+            | Event::Interest { tx, .. }
+            | Event::Transfer { tx, .. } => *tx,
+        }
+    }
+
+    /// Transaction type the event originates from
+    pub fn kind(&self) -> TransactionType {
+        match self {
+            Event::Deposit { .. } => TransactionType::Deposit,
+            Event::Withdrawal { .. } => TransactionType::Withdrawal,
+            Event::Dispute { .. } => TransactionType::Dispute,
+            Event::Resolve { .. } => TransactionType::Resolve,
+            Event::Chargeback { .. } => TransactionType::Chargeback,
+            Event::Lock { .. } => TransactionType::Lock,
+            Event::Unlock { .. } => TransactionType::Unlock,
+            Event::Fee { .. } => TransactionType::Fee,
+            Event::Interest { .. } => TransactionType::Interest,
+            Event::Transfer { .. } => TransactionType::Transfer,
+        }
+    }
+}
+
+impl TryFrom<Transaction> for Event {
+    type Error = KrctError;
+
+    /// The event of the transaction
+    ///
+    /// # Error
+    /// UnknownType: if the type of the transaction is not known, such a row is ignored by the
+    /// engine
+    fn try_from(tx: Transaction) -> Result<Self, Self::Error> {
+        Ok(match tx._type {
             TransactionType::Deposit => Event::Deposit {
                 client: tx.client_id,
                 tx: tx.transaction_id,
@@ -37,23 +193,99 @@ impl From<Transaction> for Event {
             TransactionType::Dispute => Event::Dispute {
                 client: tx.client_id,
                 tx: tx.transaction_id,
+                amount: tx.amount,
             },
             TransactionType::Resolve => Event::Resolve {
                 client: tx.client_id,
                 tx: tx.transaction_id,
+                amount: tx.amount,
             },
             TransactionType::Chargeback => Event::Chargeback {
                 client: tx.client_id,
                 tx: tx.transaction_id,
+                amount: tx.amount,
             },
-            _ => Event::Unknown,
+            TransactionType::Lock => Event::Lock {
+                client: tx.client_id,
+                tx: tx.transaction_id,
+            },
+            TransactionType::Unlock => Event::Unlock {
+                client: tx.client_id,
+                tx: tx.transaction_id,
+            },
+            TransactionType::Fee => Event::Fee {
+                client: tx.client_id,
+                tx: tx.transaction_id,
+                amount: tx.amount.unwrap_or_default(),
+            },
+            TransactionType::Interest => Event::Interest {
+                client: tx.client_id,
+                tx: tx.transaction_id,
+                amount: tx.amount.unwrap_or_default(),
+            },
+            TransactionType::Transfer => Event::Transfer {
+                client: tx.client_id,
+                tx: tx.transaction_id,
+                destination: tx.destination,
+                amount: tx.amount.unwrap_or_default(),
+            },
+            TransactionType::Unknown => return Err(KrctError::UnknownType),
+        })
+    }
+}
+
+impl From<&Event> for Transaction {
+    /// The transaction the event could be read from
+    fn from(event: &Event) -> Self {
+        let (amount, destination) = match *event {
+            Event::Deposit { amount, .. }
+            | Event::Withdrawal { amount, .. }
+            | Event::Fee { amount, .. }
+            | Event::Interest { amount, .. } => (Some(amount), None),
+            Event::Dispute { amount, .. }
+            | Event::Resolve { amount, .. }
+            | Event::Chargeback { amount, .. } => (amount, None),
+            Event::Transfer {
+                amount,
+                destination,
+                ..
+            } => (Some(amount), destination),
+            Event::Lock { .. } | Event::Unlock { .. } => (None, None),
+        };
+
+        Transaction {
+            _type: event.kind(),
+            client_id: event.client(),
+            transaction_id: event.tx(),
+            amount,
+            destination,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Event, Transaction, TransactionType};
+    use super::{Amount, Event, KrctError, Transaction, TransactionType};
+
+    #[test]
+    fn test_transaction_of_event() {
+        let events = [
+            Event::deposit(1, 1, Amount::from(2.0)),
+            Event::dispute(1, 1, None),
+            Event::chargeback(1, 1, Some(Amount::from(1.0))),
+            Event::lock(1, 2),
+            Event::transfer(1, 3, 2, Amount::from(0.5)),
+        ];
+        for event in events {
+            assert_eq!(Event::try_from(Transaction::from(&event)).ok(), Some(event));
+        }
+
+        let unknown = Transaction::new(TransactionType::Unknown, 1, 1, None);
+        assert!(matches!(
+            Event::try_from(unknown),
+            Err(KrctError::UnknownType)
+        ));
+    }
 
     #[test]
     fn test_deposit_event() {
@@ -61,16 +293,17 @@ mod tests {
             _type: TransactionType::Deposit,
             client_id: 1,
             transaction_id: 1,
-            amount: Some(2.0),
+            amount: Some(Amount::from(2.0)),
+            destination: None,
         };
 
         assert_eq!(
-            Event::from(tx),
-            Event::Deposit {
+            Event::try_from(tx).ok(),
+            Some(Event::Deposit {
                 client: 1,
                 tx: 1,
-                amount: 2.0
-            }
+                amount: Amount::from(2.0)
+            })
         );
     }
 
@@ -81,15 +314,16 @@ mod tests {
             client_id: 1,
             transaction_id: 1,
             amount: None,
+            destination: None,
         };
 
         assert_eq!(
-            Event::from(tx),
-            Event::Deposit {
+            Event::try_from(tx).ok(),
+            Some(Event::Deposit {
                 client: 1,
                 tx: 1,
-                amount: 0.0
-            }
+                amount: Amount::from(0.0)
+            })
         );
     }
 
@@ -99,16 +333,17 @@ mod tests {
             _type: TransactionType::Withdrawal,
             client_id: 1,
             transaction_id: 1,
-            amount: Some(2.0),
+            amount: Some(Amount::from(2.0)),
+            destination: None,
         };
 
         assert_eq!(
-            Event::from(tx),
-            Event::Withdrawal {
+            Event::try_from(tx).ok(),
+            Some(Event::Withdrawal {
                 client: 1,
                 tx: 1,
-                amount: 2.0
-            }
+                amount: Amount::from(2.0)
+            })
         );
     }
 
@@ -119,15 +354,16 @@ mod tests {
             client_id: 1,
             transaction_id: 1,
             amount: None,
+            destination: None,
         };
 
         assert_eq!(
-            Event::from(tx),
-            Event::Withdrawal {
+            Event::try_from(tx).ok(),
+            Some(Event::Withdrawal {
                 client: 1,
                 tx: 1,
-                amount: 0.0
-            }
+                amount: Amount::from(0.0)
+            })
         );
     }
 
@@ -138,21 +374,37 @@ mod tests {
             client_id: 1,
             transaction_id: 1,
             amount: None,
+            destination: None,
         };
 
-        assert_eq!(Event::from(tx), Event::Dispute { client: 1, tx: 1 });
+        assert_eq!(
+            Event::try_from(tx).ok(),
+            Some(Event::Dispute {
+                client: 1,
+                tx: 1,
+                amount: None
+            })
+        );
     }
 
     #[test]
-    fn test_dispute_event_amount_is_ignored() {
+    fn test_dispute_event_with_amount() {
         let tx = Transaction {
             _type: TransactionType::Dispute,
             client_id: 1,
             transaction_id: 1,
-            amount: Some(2.0),
+            amount: Some(Amount::from(2.0)),
+            destination: None,
         };
 
-        assert_eq!(Event::from(tx), Event::Dispute { client: 1, tx: 1 });
+        assert_eq!(
+            Event::try_from(tx).ok(),
+            Some(Event::Dispute {
+                client: 1,
+                tx: 1,
+                amount: Some(Amount::from(2.0))
+            })
+        );
     }
 
     #[test]
@@ -162,21 +414,37 @@ mod tests {
             client_id: 1,
             transaction_id: 1,
             amount: None,
+            destination: None,
         };
 
-        assert_eq!(Event::from(tx), Event::Resolve { client: 1, tx: 1 });
+        assert_eq!(
+            Event::try_from(tx).ok(),
+            Some(Event::Resolve {
+                client: 1,
+                tx: 1,
+                amount: None
+            })
+        );
     }
 
     #[test]
-    fn test_resolve_event_amount_is_ignored() {
+    fn test_resolve_event_with_amount() {
         let tx = Transaction {
             _type: TransactionType::Resolve,
             client_id: 1,
             transaction_id: 1,
-            amount: Some(2.0),
+            amount: Some(Amount::from(2.0)),
+            destination: None,
         };
 
-        assert_eq!(Event::from(tx), Event::Resolve { client: 1, tx: 1 });
+        assert_eq!(
+            Event::try_from(tx).ok(),
+            Some(Event::Resolve {
+                client: 1,
+                tx: 1,
+                amount: Some(Amount::from(2.0))
+            })
+        );
     }
 
     #[test]
@@ -186,20 +454,129 @@ mod tests {
             client_id: 1,
             transaction_id: 1,
             amount: None,
+            destination: None,
         };
 
-        assert_eq!(Event::from(tx), Event::Chargeback { client: 1, tx: 1 });
+        assert_eq!(
+            Event::try_from(tx).ok(),
+            Some(Event::Chargeback {
+                client: 1,
+                tx: 1,
+                amount: None
+            })
+        );
     }
 
     #[test]
-    fn test_chargeback_event_amount_is_ignored() {
+    fn test_chargeback_event_with_amount() {
         let tx = Transaction {
             _type: TransactionType::Chargeback,
             client_id: 1,
             transaction_id: 1,
-            amount: Some(2.0),
+            amount: Some(Amount::from(2.0)),
+            destination: None,
+        };
+
+        assert_eq!(
+            Event::try_from(tx).ok(),
+            Some(Event::Chargeback {
+                client: 1,
+                tx: 1,
+                amount: Some(Amount::from(2.0))
+            })
+        );
+    }
+
+    #[test]
+    fn test_lock_event() {
+        let tx = Transaction {
+            _type: TransactionType::Lock,
+            client_id: 1,
+            transaction_id: 1,
+            amount: None,
+            destination: None,
+        };
+
+        assert_eq!(
+            Event::try_from(tx).ok(),
+            Some(Event::Lock { client: 1, tx: 1 })
+        );
+    }
+
+    #[test]
+    fn test_unlock_event() {
+        let tx = Transaction {
+            _type: TransactionType::Unlock,
+            client_id: 1,
+            transaction_id: 1,
+            amount: None,
+            destination: None,
         };
 
-        assert_eq!(Event::from(tx), Event::Chargeback { client: 1, tx: 1 });
+        assert_eq!(
+            Event::try_from(tx).ok(),
+            Some(Event::Unlock { client: 1, tx: 1 })
+        );
+    }
+
+    #[test]
+    fn test_fee_event() {
+        let tx = Transaction {
+            _type: TransactionType::Fee,
+            client_id: 1,
+            transaction_id: 1,
+            amount: Some(Amount::from(0.5)),
+            destination: None,
+        };
+
+        assert_eq!(
+            Event::try_from(tx).ok(),
+            Some(Event::Fee {
+                client: 1,
+                tx: 1,
+                amount: Amount::from(0.5)
+            })
+        );
+    }
+
+    #[test]
+    fn test_interest_event() {
+        let tx = Transaction {
+            _type: TransactionType::Interest,
+            client_id: 1,
+            transaction_id: 1,
+            amount: Some(Amount::from(0.5)),
+            destination: None,
+        };
+
+        assert_eq!(
+            Event::try_from(tx).ok(),
+            Some(Event::Interest {
+                client: 1,
+                tx: 1,
+                amount: Amount::from(0.5)
+            })
+        );
+    }
+
+    #[test]
+    fn test_transfer_event() {
+        let tx = Transaction {
+            _type: TransactionType::Transfer,
+            client_id: 1,
+            transaction_id: 1,
+            amount: Some(Amount::from(2.0)),
+            destination: Some(2),
+        };
+
+        assert_eq!(
+            Event::try_from(tx).ok(),
+            Some(Event::Transfer {
+                client: 1,
+                tx: 1,
+                destination: Some(2),
+                amount: Amount::from(2.0)
+            })
+        );
     }
 }
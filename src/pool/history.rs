@@ -1,61 +1,199 @@
 use super::amount::Amount;
+use super::rejection::Reason;
 use std::collections::HashMap;
 
 /// State of a transaction history to indicate if a transaction is
-/// * Recorded: base state
-/// * Held: the corresponding transaction is under dispute
-/// * ChargedBack: the corresponding transaction is changed back
-#[derive(PartialEq, Debug)]
-pub(in crate::pool) enum State {
+/// * Recorded: base state, nothing is under dispute
+/// * Held: the corresponding transaction (or a part of it) is under dispute
+/// * ChargedBack: the corresponding transaction (or a part of it) is changed back, and nothing
+///   is under dispute
+#[derive(PartialEq, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum State {
     Recorded,
     Held,
     ChargedBack,
 }
 
-/// Incoming transaction history to record all incoming amounts to be able to dispute a previous
-/// transaction
+/// Direction of the money flow of a recorded transaction
+/// * Incoming: a deposit, the money flowed to the client
+/// * Outgoing: a withdrawal, the money flowed from the client
+#[derive(PartialEq, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Incoming,
+    Outgoing,
+}
+
+/// A transaction recorded in the history. Disputes, resolves and chargebacks could affect only a
+/// part of the transaction, so the disputed (held) and the charged back (reversed) amounts are
+/// tracked separately.
+#[derive(PartialEq, Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub(in crate::pool) struct Record {
+    pub(in crate::pool) amount: Amount,
+    pub(in crate::pool) direction: Direction,
+    pub(in crate::pool) held: Amount,
+    pub(in crate::pool) reversed: Amount,
+    pub(in crate::pool) state: State,
+}
+
+impl Record {
+    /// The amount, which is neither under dispute nor charged back, so it could be still disputed
+    pub(in crate::pool) fn disputable(&self) -> Amount {
+        self.amount
+            .checked_sub(self.held)
+            .and_then(|amount| amount.checked_sub(self.reversed))
+            .unwrap_or(Amount::ZERO)
+    }
+
+    /// Validates the requested amount against the given limit. Without a requested amount the
+    /// whole limit is taken.
+    ///
+    /// # Error
+    /// * InvalidState: if there is nothing to take
+    /// * InvalidAmount: if the requested amount is not positive or exceeds the limit
+    pub(in crate::pool) fn take(
+        limit: Amount,
+        requested: Option<Amount>,
+    ) -> Result<Amount, Reason> {
+        match requested {
+            _ if limit <= Amount::ZERO => Err(Reason::InvalidState),
+            None => Ok(limit),
+            Some(amount) if amount > Amount::ZERO && amount <= limit => Ok(amount),
+            Some(_) => Err(Reason::InvalidAmount),
+        }
+    }
+
+    /// Updates the state according to the held and reversed amounts
+    fn update_state(&mut self) {
+        self.state = if self.held > Amount::ZERO {
+            State::Held
+        } else if self.reversed > Amount::ZERO {
+            State::ChargedBack
+        } else {
+            State::Recorded
+        };
+    }
+}
+
+/// Transaction history to record all incoming and outgoing amounts to be able to dispute a
+/// previous transaction
 #[derive(Default, Debug)]
 #[cfg_attr(test, derive(PartialEq))]
-pub(in crate::pool) struct History<K, V>(HashMap<K, (Amount<V>, State)>)
+pub(in crate::pool) struct History<K>(HashMap<K, Record>)
 where
     K: Eq + std::hash::Hash;
 
-impl<K, V> History<K, V>
+impl<K> History<K>
 where
     K: Eq + std::hash::Hash,
 {
-    /// Add an incoming transaction to history
-    pub(in crate::pool) fn insert(&mut self, id: K, amount: Amount<V>) {
-        self.0.insert(id, (amount, State::Recorded));
+    /// Add a transaction to history
+    pub(in crate::pool) fn insert(&mut self, id: K, amount: Amount, direction: Direction) {
+        self.0.insert(
+            id,
+            Record {
+                amount,
+                direction,
+                held: Amount::ZERO,
+                reversed: Amount::ZERO,
+                state: State::Recorded,
+            },
+        );
     }
 
-    /// Select and get an incoming transaction from history with the given state
-    pub(in crate::pool) fn select(&self, id: K, state: State) -> Option<&Amount<V>> {
-        match self.0.get(&id) {
-            Some(transaction) if transaction.1 == state => Some(&transaction.0),
-            _ => None,
-        }
+    /// Get a transaction from history
+    ///
+    /// # Error
+    /// If the transaction is not known
+    pub(in crate::pool) fn get(&self, id: &K) -> Result<&Record, Reason> {
+        self.0.get(id).ok_or(Reason::UnknownTx)
+    }
+
+    /// Copy of a transaction, if it is recorded, to be able to restore it later
+    pub(in crate::pool) fn backup(&self, id: &K) -> Option<Record> {
+        self.0.get(id).cloned()
+    }
+
+    /// Restores a transaction from a backup, a transaction without backup is removed
+    pub(in crate::pool) fn restore(&mut self, id: K, record: Option<Record>) {
+        match record {
+            Some(record) => self.0.insert(id, record),
+            None => self.0.remove(&id),
+        };
+    }
+
+    /// All the recorded transactions
+    pub(in crate::pool) fn iter(&self) -> impl Iterator<Item = (&K, &Record)> {
+        self.0.iter()
+    }
+
+    /// Holds the given amount of a transaction, it is under dispute
+    pub(in crate::pool) fn hold(&mut self, id: &K, amount: Amount) {
+        self.update(id, |record| {
+            record.held = record.held.checked_add(amount)?;
+            Some(())
+        });
+    }
+
+    /// Releases the given held amount of a transaction, the dispute is resolved
+    pub(in crate::pool) fn release(&mut self, id: &K, amount: Amount) {
+        self.update(id, |record| {
+            record.held = record.held.checked_sub(amount)?;
+            Some(())
+        });
     }
 
-    /// Set the state of an incoming transaction in the history
-    pub(in crate::pool) fn set_state(&mut self, id: K, state: State) {
-        if let Some(transaction) = self.0.get_mut(&id) {
-            transaction.1 = state;
+    /// Charges back the given held amount of a transaction
+    pub(in crate::pool) fn reverse(&mut self, id: &K, amount: Amount) {
+        self.update(id, |record| {
+            record.held = record.held.checked_sub(amount)?;
+            record.reversed = record.reversed.checked_add(amount)?;
+            Some(())
+        });
+    }
+
+    /// Updates a transaction with the given function and refreshes its state
+    fn update<F>(&mut self, id: &K, update: F)
+    where
+        F: FnOnce(&mut Record) -> Option<()>,
+    {
+        if let Some(record) = self.0.get_mut(id) {
+            // The amounts are bounded by the transaction amount, so they cannot overflow
+            let _ = update(record);
+            record.update_state();
         }
     }
 }
 
 #[cfg(test)]
-impl<K, V, const N: usize> From<[(K, V, State); N]> for History<K, V>
+impl<K, const N: usize> From<[(K, f64, State); N]> for History<K>
+where
+    K: Eq + std::hash::Hash + std::default::Default + Clone,
+{
+    fn from(data: [(K, f64, State); N]) -> Self {
+        History::from(data.map(|(key, value, state)| (key, Direction::Incoming, value, state)))
+    }
+}
+
+#[cfg(test)]
+impl<K, const N: usize> From<[(K, Direction, f64, State); N]> for History<K>
 where
     K: Eq + std::hash::Hash + std::default::Default + Clone,
-    V: std::default::Default,
 {
-    fn from(data: [(K, V, State); N]) -> Self {
+    fn from(data: [(K, Direction, f64, State); N]) -> Self {
         let mut history = History::default();
-        for (key, value, state) in data {
-            history.insert(key.clone(), Amount(value));
-            history.set_state(key, state);
+        for (key, direction, value, state) in data {
+            let amount = Amount::from(value);
+            history.insert(key.clone(), amount, direction);
+            match state {
+                State::Recorded => (),
+                State::Held => history.hold(&key, amount),
+                State::ChargedBack => {
+                    history.hold(&key, amount);
+                    history.reverse(&key, amount);
+                }
+            }
         }
         history
     }
@@ -63,20 +201,86 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::{Amount, History, State};
+    use super::{Amount, Direction, History, Reason, Record, State};
 
     #[test]
-    fn test_selecting_from_history_with_hit() {
+    fn test_getting_from_history_with_hit() {
         let mut history = History::default();
-        history.insert(1, Amount(1.0));
-        assert!(history.select(1, State::Recorded).is_some());
+        history.insert(1, Amount::from(1.0), Direction::Incoming);
+        assert_eq!(
+            history.get(&1),
+            Ok(&Record {
+                amount: Amount::from(1.0),
+                direction: Direction::Incoming,
+                held: Amount::ZERO,
+                reversed: Amount::ZERO,
+                state: State::Recorded,
+            })
+        );
+    }
+
+    #[test]
+    fn test_getting_unknown_transaction_from_history() {
+        let history = History::<u32>::default();
+        assert_eq!(history.get(&1), Err(Reason::UnknownTx));
     }
 
     #[test]
-    fn test_selecting_from_history_with_no_hit() {
+    fn test_partial_dispute_states() {
         let mut history = History::default();
-        history.insert(1, Amount(1.0));
-        history.set_state(1, State::Held);
-        assert!(history.select(1, State::Recorded).is_none());
+        history.insert(1, Amount::from(10.0), Direction::Incoming);
+
+        history.hold(&1, Amount::from(4.0));
+        let record = history.get(&1).unwrap();
+        assert_eq!(record.state, State::Held);
+        assert_eq!(record.disputable(), Amount::from(6.0));
+
+        history.reverse(&1, Amount::from(1.0));
+        let record = history.get(&1).unwrap();
+        assert_eq!(record.state, State::Held);
+        assert_eq!(record.disputable(), Amount::from(6.0));
+
+        history.release(&1, Amount::from(3.0));
+        let record = history.get(&1).unwrap();
+        assert_eq!(record.state, State::ChargedBack);
+        assert_eq!(record.disputable(), Amount::from(9.0));
+    }
+
+    #[test]
+    fn test_restoring_from_backup() {
+        let mut history = History::default();
+        history.insert(1, Amount::from(1.0), Direction::Incoming);
+        let backup = history.backup(&1);
+        history.hold(&1, Amount::from(1.0));
+        history.restore(1, backup);
+        assert_eq!(
+            history.get(&1).map(|record| &record.state),
+            Ok(&State::Recorded)
+        );
+
+        let backup = history.backup(&2);
+        assert_eq!(backup, None);
+        history.insert(2, Amount::from(1.0), Direction::Outgoing);
+        history.restore(2, backup);
+        assert_eq!(history.get(&2), Err(Reason::UnknownTx));
+    }
+
+    #[test]
+    fn test_taking_amount() {
+        let limit = Amount::from(2.0);
+        assert_eq!(Record::take(limit, None), Ok(limit));
+        assert_eq!(
+            Record::take(limit, Some(Amount::from(1.5))),
+            Ok(Amount::from(1.5))
+        );
+        assert_eq!(
+            Record::take(limit, Some(Amount::from(2.5))),
+            Err(Reason::InvalidAmount)
+        );
+        assert_eq!(
+            Record::take(limit, Some(Amount::from(-1.0))),
+            Err(Reason::InvalidAmount)
+        );
+        assert_eq!(Record::take(Amount::ZERO, None), Err(Reason::InvalidState));
     }
 }
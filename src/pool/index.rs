@@ -0,0 +1,310 @@
+use serde::de::Error;
+use serde::ser::SerializeSeq;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// Number of transaction ids covered by a single page
+const PAGE_SIZE: usize = 1 << 16;
+/// Number of words in a page, each word stores 64 transaction ids
+const PAGE_WORDS: usize = PAGE_SIZE / u64::BITS as usize;
+
+/// Global index of the already used transaction ids and their owner client across all the
+/// clients.
+///
+/// The id space is split into pages of 65536 ids, and a page is allocated only when an id from its
+/// range arrives. A page holds a bitset of the used ids and the owner client id of each of them,
+/// which is about 2 bytes per id. Therefore, even hundreds of millions of ids fit into a few
+/// hundreds of megabytes, and an unused id range takes no memory at all.
+///
+/// A clone shares the pages, a page is copied only when an id is inserted into a shared page, so a
+/// snapshot of the index for a `State` is cheap. The index is serialized page by page in the same
+/// compact form, see `PageState`.
+#[derive(Default, Clone, PartialEq)]
+pub(in crate::pool) struct TxIndex {
+    pages: HashMap<u32, Arc<Page>>,
+}
+
+/// A range of transaction ids in the index
+#[derive(Clone, PartialEq)]
+struct Page {
+    used: [u64; PAGE_WORDS],
+    owners: [u16; PAGE_SIZE],
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self {
+            used: [0; PAGE_WORDS],
+            owners: [0; PAGE_SIZE],
+        }
+    }
+}
+
+impl TxIndex {
+    /// Records the given transaction id as owned by the given client.
+    ///
+    /// Returns `false`, if the id has been already recorded before, in that case the owner is not
+    /// changed.
+    pub(in crate::pool) fn insert(&mut self, tx: u32, owner: u16) -> bool {
+        let (page, offset) = Self::position(tx);
+        let page = self.pages.entry(page).or_default();
+        let (word, bit) = Self::bit(offset);
+        if page.used[word] & bit != 0 {
+            return false;
+        }
+        let page = Arc::make_mut(page);
+        page.used[word] |= bit;
+        page.owners[offset] = owner;
+
+        true
+    }
+
+    /// The client owning the given transaction id, if the id has been recorded
+    pub(in crate::pool) fn owner(&self, tx: u32) -> Option<u16> {
+        let (page, offset) = Self::position(tx);
+        let page = self.pages.get(&page)?;
+        let (word, bit) = Self::bit(offset);
+
+        (page.used[word] & bit != 0).then(|| page.owners[offset])
+    }
+
+    /// Adds all the transaction ids of the given index, the already recorded ids keep their owner
+    pub(in crate::pool) fn extend(&mut self, other: TxIndex) {
+        for (number, page) in other.pages {
+            let current = match self.pages.get_mut(&number) {
+                Some(current) => Arc::make_mut(current),
+                None => {
+                    self.pages.insert(number, page);
+                    continue;
+                }
+            };
+            for offset in page.offsets() {
+                let (word, bit) = Self::bit(offset);
+                if current.used[word] & bit == 0 {
+                    current.used[word] |= bit;
+                    current.owners[offset] = page.owners[offset];
+                }
+            }
+        }
+    }
+
+    /// Page and the offset in the page of a transaction id
+    fn position(tx: u32) -> (u32, usize) {
+        (tx / PAGE_SIZE as u32, tx as usize % PAGE_SIZE)
+    }
+
+    /// Word and the bit mask in the word of an offset in the bitset of a page
+    fn bit(offset: usize) -> (usize, u64) {
+        (
+            offset / u64::BITS as usize,
+            1 << (offset % u64::BITS as usize),
+        )
+    }
+}
+
+impl Page {
+    /// Offsets of the used ids in the page in order
+    fn offsets(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..PAGE_SIZE).filter(move |&offset| {
+            let (word, bit) = TxIndex::bit(offset);
+            self.used[word] & bit != 0
+        })
+    }
+
+    /// The serialized form of the page with the given number
+    fn state(&self, page: u32) -> PageState {
+        let mut state = PageState {
+            page,
+            used: String::new(),
+            owners: String::new(),
+        };
+        for (index, word) in self.used.iter().enumerate().filter(|(_, &word)| word != 0) {
+            let _ = write!(state.used, "{:03x}{:016x}", index, word);
+        }
+        for offset in self.offsets() {
+            let _ = write!(state.owners, "{:04x}", self.owners[offset]);
+        }
+
+        state
+    }
+
+    /// The page of the given serialized form, or `None`, if it is invalid
+    fn from_state(state: &PageState) -> Option<Self> {
+        if !state.used.is_ascii()
+            || !state.owners.is_ascii()
+            || !state.used.len().is_multiple_of(19)
+        {
+            return None;
+        }
+        let mut page = Page::default();
+        for word in (0..state.used.len()).step_by(19) {
+            let index = usize::from_str_radix(&state.used[word..word + 3], 16).ok()?;
+            *page.used.get_mut(index)? =
+                u64::from_str_radix(&state.used[word + 3..word + 19], 16).ok()?;
+        }
+        let used = page.offsets().collect::<Vec<_>>();
+        if state.owners.len() != used.len() * 4 {
+            return None;
+        }
+        for (owner, offset) in used.into_iter().enumerate() {
+            page.owners[offset] =
+                u16::from_str_radix(&state.owners[owner * 4..owner * 4 + 4], 16).ok()?;
+        }
+
+        Some(page)
+    }
+}
+
+/// Serialized form of a page of the index. The used ids are the non-zero words of the bitset,
+/// each of them is its index in 3 hex digits followed by the word in 16 hex digits, so a sparse
+/// page stays short. The owners are the owner client ids of the used ids in order, each of them
+/// in 4 hex digits.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PageState {
+    page: u32,
+    used: String,
+    owners: String,
+}
+
+impl serde::Serialize for TxIndex {
+    /// The pages are serialized one by one in order, so the whole index is not copied
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut pages = self.pages.keys().copied().collect::<Vec<_>>();
+        pages.sort_unstable();
+        let mut sequence = serializer.serialize_seq(Some(pages.len()))?;
+        for page in pages {
+            sequence.serialize_element(&self.pages[&page].state(page))?;
+        }
+
+        sequence.end()
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for TxIndex {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Pages;
+
+        impl<'de> serde::de::Visitor<'de> for Pages {
+            type Value = TxIndex;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a sequence of transaction id pages")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut pages: A,
+            ) -> Result<TxIndex, A::Error> {
+                let mut index = TxIndex::default();
+                while let Some(state) = pages.next_element::<PageState>()? {
+                    let page = Page::from_state(&state).ok_or_else(|| {
+                        A::Error::custom(format!("Invalid page of transaction ids: {}", state.page))
+                    })?;
+                    if index.pages.insert(state.page, Arc::new(page)).is_some() {
+                        return Err(A::Error::custom(format!(
+                            "Duplicate page of transaction ids: {}",
+                            state.page
+                        )));
+                    }
+                }
+
+                Ok(index)
+            }
+        }
+
+        deserializer.deserialize_seq(Pages)
+    }
+}
+
+impl std::fmt::Debug for TxIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TxIndex")
+            .field("pages", &self.pages.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TxIndex;
+
+    #[test]
+    fn test_insert() {
+        let mut index = TxIndex::default();
+        assert!(index.insert(1, 1));
+        assert!(index.insert(2, 2));
+        assert!(index.insert(64, 0));
+        assert_eq!(index.owner(1), Some(1));
+        assert_eq!(index.owner(2), Some(2));
+        assert_eq!(index.owner(64), Some(0));
+        assert_eq!(index.owner(3), None);
+    }
+
+    #[test]
+    fn test_duplicate_insert() {
+        let mut index = TxIndex::default();
+        assert!(index.insert(1, 1));
+        assert!(!index.insert(1, 2));
+        assert_eq!(index.owner(1), Some(1));
+    }
+
+    #[test]
+    fn test_sparse_ids() {
+        let mut index = TxIndex::default();
+        assert!(index.insert(0, 1));
+        assert!(index.insert(u32::MAX, u16::MAX));
+        assert!(!index.insert(u32::MAX, 1));
+        assert_eq!(index.owner(u32::MAX), Some(u16::MAX));
+        assert_eq!(index.owner(u32::MAX - 1), None);
+        assert_eq!(index.pages.len(), 2);
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut index = TxIndex::default();
+        assert!(index.insert(1, 1));
+        assert!(index.insert(65, 0x1234));
+        assert!(index.insert(u32::MAX, u16::MAX));
+        let json = serde_json::to_string(&index).expect("Failed to serialize index");
+        assert_eq!(
+            json,
+            "[{\"page\":0,\"used\":\"0000000000000000002\
+            0010000000000000002\",\"owners\":\"00011234\"},\
+            {\"page\":65535,\"used\":\"3ff8000000000000000\",\"owners\":\"ffff\"}]"
+        );
+        let read = serde_json::from_str::<TxIndex>(&json).expect("Failed to deserialize index");
+        assert!(read == index);
+        assert_eq!(read.owner(65), Some(0x1234));
+
+        for invalid in [
+            "[{\"page\":0,\"used\":\"0000000000000000002\",\"owners\":\"\"}]",
+            "[{\"page\":0,\"used\":\"4000000000000000001\",\"owners\":\"0001\"}]",
+            "[{\"page\":0,\"used\":\"00000000000000000x2\",\"owners\":\"0001\"}]",
+        ] {
+            assert!(
+                serde_json::from_str::<TxIndex>(invalid).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_clone_shares_pages() {
+        let mut index = TxIndex::default();
+        assert!(index.insert(1, 1));
+        let snapshot = index.clone();
+        assert!(index.insert(2, 2));
+        assert_eq!(snapshot.owner(2), None);
+        assert_eq!(index.owner(2), Some(2));
+
+        let mut restored = TxIndex::default();
+        assert!(restored.insert(3, 3));
+        assert!(restored.insert(1, 4));
+        restored.extend(index);
+        assert_eq!(restored.owner(1), Some(4));
+        assert_eq!(restored.owner(2), Some(2));
+        assert_eq!(restored.owner(3), Some(3));
+    }
+}
@@ -1,40 +1,147 @@
-use super::{Client, Event, Pool};
+use super::worker::Clients;
+use super::{Client, Pool};
+use crate::error::KrctError;
 
 impl Pool {
-    /// Start shutting down and joining client threads. Returning an iterator, so a client could be
-    /// dumped as soon as it finished processing
-    pub fn iter(self) -> impl std::iter::Iterator<Item = Client> {
-        self.clients.into_iter().map(|(id, (tx, client))| {
-            tx.send(Event::Finish).expect("Worker died");
-            client
-                .join()
-                .unwrap_or_else(|err| panic!("Could not join {} worker: {:?}", id, err))
-        })
+    /// Start shutting down and joining worker threads. Returning an iterator, so the clients of a
+    /// worker could be dumped as soon as it finished processing.
+    ///
+    /// A client failed while handling its events is yielded as an error, and if a whole worker
+    /// died, then all of its clients are yielded as errors, the other clients are not affected.
+    /// The tasks of an async pool could be joined only on a multi-threaded tokio runtime,
+    /// otherwise a single `KrctError::Runtime` is yielded instead of blocking the runtime.
+    pub fn iter(self) -> impl std::iter::Iterator<Item = crate::Result<Client>> {
+        let Pool { workers, seen, .. } = self;
+        let shards = workers.len();
+        workers
+            .into_iter()
+            .enumerate()
+            .flat_map(move |(shard, worker)| match worker.joinable() {
+                Ok(()) => Self::collect(worker.join(), &seen, shard, shards),
+                Err(err) => vec![Err(err)],
+            })
+    }
+
+    /// Shutting down and joining the workers without blocking the async context
+    #[cfg(feature = "async")]
+    pub async fn join_async(self) -> Vec<crate::Result<Client>> {
+        let Pool { workers, seen, .. } = self;
+        let shards = workers.len();
+        let mut clients = Vec::new();
+        for (shard, worker) in workers.into_iter().enumerate() {
+            clients.extend(Self::collect(
+                worker.join_async().await,
+                &seen,
+                shard,
+                shards,
+            ));
+        }
+
+        clients
     }
 
     /// Returns an iterator for the Clients in a sorted form. In this scenario all the client
     /// handlers has to be finished first
-    pub fn sorted(self) -> impl std::iter::Iterator<Item = Client> {
-        let mut clients = self.iter().collect::<Vec<Client>>();
-        clients.sort_by(|first, second| first.id.cmp(&second.id));
+    pub fn sorted(self) -> impl std::iter::Iterator<Item = crate::Result<Client>> {
+        Self::sort(self.iter().collect())
+    }
+
+    /// Sorts the given clients by the client identifier, the failed clients are placed first
+    pub(crate) fn sort(
+        mut clients: Vec<crate::Result<Client>>,
+    ) -> impl std::iter::Iterator<Item = crate::Result<Client>> {
+        clients.sort_by_key(|client| client.as_ref().ok().map(|client| client.id));
 
         clients.into_iter()
     }
+
+    /// Turns the clients of a joined worker into results. If the worker died, then each client
+    /// seen by the pool in the shard of the worker is failed.
+    fn collect(
+        clients: Result<Clients, ()>,
+        seen: &[u64],
+        shard: usize,
+        shards: usize,
+    ) -> Vec<crate::Result<Client>> {
+        match clients {
+            Ok(clients) => clients
+                .into_iter()
+                .map(|(id, client)| client.ok_or(KrctError::Worker(id)))
+                .collect(),
+            Err(()) => Self::shard(seen, shard, shards)
+                .map(|id| Err(KrctError::Worker(id)))
+                .collect(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::{Client, Pool};
+    use crate::error::KrctError;
+    use crate::pool::{Event, SEEN_WORDS};
+    use crate::{Config, Krct, OutputFormat};
 
     #[test]
     fn test_pool_join() {
-        let mut clients = std::collections::HashMap::new();
+        let mut pool = Pool::new(&Config {
+            workers: Some(2),
+            ..Config::default()
+        });
         for id in 1..4 {
-            let (tx, rx) = std::sync::mpsc::channel();
-            clients.insert(id, (tx, std::thread::spawn(move || Client::new(id, rx))));
+            assert!(pool
+                .handle(Event::Lock {
+                    client: id,
+                    tx: id as u32
+                })
+                .is_ok());
         }
-        let pool = Pool { clients };
 
-        assert_eq!(pool.iter().collect::<Vec<Client>>().len(), 3);
+        assert_eq!(
+            pool.iter()
+                .collect::<crate::Result<Vec<Client>>>()
+                .map(|clients| clients.len())
+                .ok(),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_clients_of_dead_worker_are_failed() {
+        let mut seen = vec![0; SEEN_WORDS];
+        for id in [1u16, 2, 3, 64] {
+            seen[id as usize / 64] |= 1 << (id as usize % 64);
+        }
+
+        let failed = Pool::collect(Err(()), &seen, 0, 2)
+            .into_iter()
+            .map(|client| match client {
+                Err(KrctError::Worker(id)) => Some(id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(failed, vec![Some(2), Some(64)]);
+    }
+
+    #[test]
+    fn test_dump_with_failed_clients() {
+        let clients = vec![
+            Ok((1, 1.0)),
+            Err(KrctError::Worker(4)),
+            Ok((2, 2.0)),
+            Err(KrctError::Worker(3)),
+        ];
+        let mut buffer = Vec::new();
+        let result = Krct::write(clients, &mut buffer, OutputFormat::Csv);
+        assert!(
+            matches!(&result, Err(KrctError::Failed(ids)) if ids == &[3, 4]),
+            "{:?}",
+            result
+        );
+        assert_eq!(String::from_utf8_lossy(&buffer), "1,1.0\n2,2.0\n");
+        assert_eq!(
+            result.map_err(|err| err.to_string()),
+            Err("Failed clients: 3,4".to_string())
+        );
     }
 }
@@ -0,0 +1,269 @@
+use super::amount::Amount;
+use super::client::Client;
+use super::history::{Direction, State};
+use super::worker::Message;
+use super::Pool;
+use crate::config::ReportFormat;
+use crate::error::KrctError;
+use crate::TransactionType;
+
+/// An event applied by a client with the balances right after it, recorded only if the ledger is
+/// enabled
+#[derive(Debug, Clone)]
+pub(in crate::pool) struct Step {
+    pub(in crate::pool) tx: u32,
+    pub(in crate::pool) kind: TransactionType,
+    /// The direction and the counterparty of a transfer
+    pub(in crate::pool) transfer: Option<(Direction, u16)>,
+    pub(in crate::pool) amount: Amount,
+    pub(in crate::pool) available: Amount,
+    pub(in crate::pool) held: Amount,
+    pub(in crate::pool) total: Amount,
+}
+
+/// An applied event in the ledger of a client. A transfer is recorded in the ledger of both the
+/// source and the destination client with the id of the transfer, its direction and the other
+/// client as the counterparty.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct LedgerEntry {
+    client: u16,
+    tx: u32,
+    #[serde(rename = "type")]
+    kind: TransactionType,
+    direction: Option<Direction>,
+    counterparty: Option<u16>,
+    amount: Amount,
+    available: Amount,
+    held: Amount,
+    total: Amount,
+    state: Option<State>,
+}
+
+impl LedgerEntry {
+    /// Client ID of the ledger
+    pub fn client(&self) -> u16 {
+        self.client
+    }
+
+    /// Transaction ID of the event, or the referenced transaction of a dispute, resolve or
+    /// chargeback
+    pub fn tx(&self) -> u32 {
+        self.tx
+    }
+
+    /// Type of the event
+    pub fn kind(&self) -> TransactionType {
+        self.kind
+    }
+
+    /// Direction of a transfer, other events have none
+    pub fn direction(&self) -> Option<Direction> {
+        self.direction
+    }
+
+    /// The other client of a transfer, other events have none
+    pub fn counterparty(&self) -> Option<u16> {
+        self.counterparty
+    }
+
+    /// Amount affected by the event, e.g. the disputed amount of a dispute or the charged part of
+    /// a capped fee
+    pub fn amount(&self) -> Amount {
+        self.amount
+    }
+
+    /// Available amount after the event
+    pub fn available(&self) -> Amount {
+        self.available
+    }
+
+    /// Held amount after the event
+    pub fn held(&self) -> Amount {
+        self.held
+    }
+
+    /// Total amount after the event
+    pub fn total(&self) -> Amount {
+        self.total
+    }
+
+    /// Final state of the deposit or withdrawal the event records or references, other events,
+    /// e.g. a fee, have none
+    pub fn state(&self) -> Option<State> {
+        self.state.clone()
+    }
+}
+
+/// Ledger of every client, the applied events of a client in order, see `Krct::ledger`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ledger(Vec<LedgerEntry>);
+
+impl Ledger {
+    /// The entries sorted by the client identifier, and in the order of the events of a client
+    pub fn entries(&self) -> &[LedgerEntry] {
+        &self.0
+    }
+
+    /// Writes the ledger to the given writer in the given format
+    pub fn dump<W: std::io::Write>(self, writer: W, format: ReportFormat) -> crate::Result<()> {
+        self.write(writer, format).map_err(KrctError::CsvDump)
+    }
+
+    fn write<W: std::io::Write>(self, mut writer: W, format: ReportFormat) -> std::io::Result<()> {
+        match format {
+            ReportFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(writer);
+                for entry in self.0 {
+                    writer.serialize(entry)?;
+                }
+                writer.flush()?;
+            }
+            ReportFormat::Ndjson => {
+                for entry in self.0 {
+                    serde_json::to_writer(&mut writer, &entry)?;
+                    writeln!(writer)?;
+                }
+                writer.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl IntoIterator for Ledger {
+    type Item = LedgerEntry;
+    type IntoIter = std::vec::IntoIter<LedgerEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl Client {
+    /// The ledger of the client with the final state of the transactions, empty if the ledger is
+    /// not enabled
+    pub(in crate::pool) fn ledger(&self) -> impl Iterator<Item = LedgerEntry> + '_ {
+        self.ledger.iter().flatten().map(move |step| {
+            let state = match step.kind {
+                TransactionType::Deposit
+                | TransactionType::Withdrawal
+                | TransactionType::Transfer
+                | TransactionType::Dispute
+                | TransactionType::Resolve
+                | TransactionType::Chargeback => self
+                    .transaction_history
+                    .get(&step.tx)
+                    .ok()
+                    .map(|record| record.state.clone()),
+                _ => None,
+            };
+
+            LedgerEntry {
+                client: self.id,
+                tx: step.tx,
+                kind: step.kind,
+                direction: step.transfer.map(|(direction, _)| direction),
+                counterparty: step.transfer.map(|(_, counterparty)| counterparty),
+                amount: step.amount,
+                available: step.available,
+                held: step.held,
+                total: step.total,
+                state,
+            }
+        })
+    }
+}
+
+impl Pool {
+    /// Ledger of every client without stopping the workers. Like a snapshot, the ledger is
+    /// queried after all the already handled events, so it is consistent. It is empty, unless
+    /// `Config::ledger` is enabled.
+    ///
+    /// # Error
+    /// If a client failed or a worker is not running anymore, with the ids of the failed clients
+    pub fn ledger(&self) -> crate::Result<Ledger> {
+        let entries = self.gather(
+            |reply| Message::Ledger { reply },
+            |reply| reply,
+            LedgerEntry::client,
+        )?;
+
+        Ok(Ledger(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Pool;
+    use crate::{Amount, Config, Event, ReportFormat};
+
+    fn pool() -> Pool {
+        let mut pool = Pool::new(&Config {
+            ledger: true,
+            ..Config::default()
+        });
+        for event in [
+            Event::deposit(1, 1, Amount::from(10.0)),
+            Event::deposit(2, 2, Amount::from(1.0)),
+            Event::withdrawal(1, 3, Amount::from(20.0)),
+            Event::dispute(1, 1, Some(Amount::from(4.0))),
+            Event::transfer(1, 4, 2, Amount::from(1.5)),
+            Event::transfer(2, 5, 1, Amount::from(9.0)),
+            Event::fee(2, 6, Amount::from(0.25)),
+            Event::chargeback(1, 1, None),
+            // The locked destination rejects the deposit, so the withdrawal is rolled back
+            Event::transfer(2, 7, 1, Amount::from(0.5)),
+        ] {
+            pool.handle(event).expect("Failed to handle event");
+        }
+        pool
+    }
+
+    #[test]
+    fn test_csv_ledger() {
+        let mut buffer = Vec::new();
+        let ledger = pool().ledger().expect("Failed to query ledger");
+        assert!(ledger.dump(&mut buffer, ReportFormat::Csv).is_ok());
+        assert_eq!(
+            String::from_utf8_lossy(&buffer),
+            "client,tx,type,direction,counterparty,amount,available,held,total,state\n\
+            1,1,deposit,,,10.0,10.0,0.0,10.0,charged_back\n\
+            1,1,dispute,,,4.0,6.0,4.0,10.0,charged_back\n\
+            1,4,transfer,outgoing,2,1.5,4.5,4.0,8.5,recorded\n\
+            1,1,chargeback,,,4.0,4.5,0.0,4.5,charged_back\n\
+            2,2,deposit,,,1.0,1.0,0.0,1.0,recorded\n\
+            2,4,transfer,incoming,1,1.5,2.5,0.0,2.5,recorded\n\
+            2,6,fee,,,0.25,2.25,0.0,2.25,\n"
+        );
+    }
+
+    #[test]
+    fn test_ndjson_ledger() {
+        let mut buffer = Vec::new();
+        let ledger = pool().ledger().expect("Failed to query ledger");
+        assert_eq!(ledger.entries().len(), 7);
+        let entries = ledger.clone().into_iter().collect::<Vec<_>>();
+        assert_eq!(entries.as_slice(), ledger.entries());
+        assert!(ledger.dump(&mut buffer, ReportFormat::Ndjson).is_ok());
+        assert_eq!(
+            String::from_utf8_lossy(&buffer).lines().last(),
+            Some(
+                "{\"client\":2,\"tx\":6,\"type\":\"fee\",\"direction\":null,\"counterparty\":null,\
+                \"amount\":\"0.25\",\"available\":\"2.25\",\
+                \"held\":\"0.0\",\"total\":\"2.25\",\"state\":null}"
+            )
+        );
+    }
+
+    #[test]
+    fn test_ledger_is_disabled_by_default() {
+        let mut pool = Pool::new(&Config::default());
+        pool.handle(Event::deposit(1, 1, Amount::from(1.0)))
+            .expect("Failed to handle event");
+        assert_eq!(
+            pool.ledger().map(|ledger| ledger.entries().len()).ok(),
+            Some(0)
+        );
+    }
+}
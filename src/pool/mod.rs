@@ -1,64 +1,247 @@
+mod account;
 mod amount;
 mod client;
 mod event;
 mod history;
+mod index;
 mod iter;
+mod ledger;
+mod query;
+mod rejection;
+mod state;
 #[cfg(test)]
 mod test;
+mod transfer;
+mod worker;
 
+use crate::config::Config;
 use crate::error::KrctError;
 use crate::pool::client::Client;
+use crate::pool::index::TxIndex;
+use crate::pool::worker::{Handle, Message, Worker};
+pub use account::Account;
+pub use amount::Amount;
+pub use client::LockReason;
 pub use event::Event;
+pub use history::{Direction, State as TransactionState};
+pub use ledger::{Ledger, LedgerEntry};
+pub use rejection::{Reason, Rejection, Rejections};
+pub use state::State;
 use std::collections::HashMap;
 use std::sync::mpsc;
-use std::thread::JoinHandle;
+use std::time::Duration;
 
-/// Pre-allocating space for N clients
-const CLIENT_PREALLOCATE: usize = 20;
+/// Default capacity of the queue of a worker
+pub(crate) const QUEUE_CAPACITY: usize = 1024;
+/// Number of words in the bitset of the client ids, each word stores 64 client ids
+const SEEN_WORDS: usize = (u16::MAX as usize + 1) / u64::BITS as usize;
 
 pub struct Pool {
-    clients: HashMap<u16, (mpsc::Sender<Event>, JoinHandle<Client>)>,
+    workers: Vec<Handle>,
+    blocked: Duration,
+    rejections: Option<(mpsc::Sender<Rejection>, Option<Rejections>)>,
+    transactions: TxIndex,
+    transfers: HashMap<u32, u16>,
+    /// Bitset of the client ids, which have been sent to a worker, so the clients of a dead worker
+    /// could be still named
+    seen: Vec<u64>,
 }
 
 impl Default for Pool {
     fn default() -> Self {
-        Self {
-            clients: HashMap::with_capacity(CLIENT_PREALLOCATE),
-        }
+        Self::new(&Config::default())
     }
 }
 
 impl Pool {
-    /// The client pool is responsible handling clients and dispatches the events to the
-    /// corresponding client.
+    /// Constructing a new client pool with the given configuration. The worker threads are
+    /// started at once, by default one worker for each CPU. Each worker has a bounded queue, by
+    /// default for 1024 events.
+    pub fn new(config: &Config) -> Self {
+        Self::with_workers(config, |worker, capacity| {
+            let (tx, rx) = mpsc::sync_channel(capacity);
+            Handle::Thread(tx, std::thread::spawn(move || worker.start_handling(rx)))
+        })
+    }
+
+    /// Constructing a new client pool with the given configuration, where the workers are tokio
+    /// tasks. The pool itself blocks on full worker queues, so it has to be used outside of the
+    /// async context, e.g. in `tokio::task::spawn_blocking`.
+    #[cfg(feature = "async")]
+    pub fn new_async(config: &Config) -> Self {
+        Self::with_workers(config, |worker, capacity| {
+            let (tx, rx) = tokio::sync::mpsc::channel(capacity);
+            Handle::Task(tx, tokio::spawn(worker.start_handling_async(rx)))
+        })
+    }
+
+    /// Constructing a new client pool, where the workers are started by the given function
+    fn with_workers<F>(config: &Config, start: F) -> Self
+    where
+        F: Fn(Worker, usize) -> Handle,
+    {
+        let rejections = config.rejections.then(|| {
+            let (tx, rx) = mpsc::channel();
+            (tx, Some(Rejections(rx)))
+        });
+        let workers = config
+            .workers
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, usize::from))
+            .max(1);
+        let capacity = config.queue_capacity.unwrap_or(QUEUE_CAPACITY).max(1);
+        let workers = (0..workers)
+            .map(|_| {
+                let reporter = rejections.as_ref().map(|(reporter, _)| reporter.clone());
+                start(
+                    Worker::new(reporter, config.policy.clone(), config.ledger),
+                    capacity,
+                )
+            })
+            .collect();
+
+        Self {
+            workers,
+            blocked: Duration::ZERO,
+            rejections,
+            transactions: TxIndex::default(),
+            transfers: HashMap::new(),
+            seen: vec![0; SEEN_WORDS],
+        }
+    }
+
+    /// Total time spent blocked on sending events to workers with full queues, i.e. the time the
+    /// reader waited for the workers to catch up
+    pub fn blocked(&self) -> Duration {
+        self.blocked
+    }
+
+    /// Takes the stream of the rejected events. It is available only once and only if the
+    /// rejections are collected.
+    pub fn rejections(&mut self) -> Option<Rejections> {
+        self.rejections
+            .as_mut()
+            .and_then(|(_, rejections)| rejections.take())
+    }
+
+    /// The client pool is responsible handling clients and dispatches the events to the worker of
+    /// the corresponding client. The transaction ids of deposits, withdrawals, fees, interests and
+    /// transfers have to be unique
+    /// across all the clients, a transaction reusing an id is rejected before it reaches the
+    /// client. An id is taken even if the transaction is rejected later by the client.
+    /// Similarly, a dispute, resolve or chargeback referencing a transaction of another client is
+    /// rejected by the pool. Lock and unlock events do not take a transaction id.
+    ///
+    /// A transfer, and a dispute, resolve or chargeback of a transfer affect two clients, these
+    /// are coordinated by the pool to be applied on both clients or on none of them.
     ///
     /// # Error
     /// If an event arrives, which cannot be handled by the client.
     pub fn handle(&mut self, event: Event) -> crate::Result<()> {
-        let client = match event {
-            Event::Deposit { client, .. } => self.get_or_insert(client),
-            Event::Withdrawal { client, .. } => self.get_or_insert(client),
-            Event::Dispute { client, .. } => self.get_or_insert(client),
-            Event::Resolve { client, .. } => self.get_or_insert(client),
-            Event::Chargeback { client, .. } => self.get_or_insert(client),
-            _ => return Ok(()),
-        };
-
-        client.0.send(event).map_err(KrctError::Handler)
+        match event {
+            Event::Deposit { client, tx, .. }
+            | Event::Withdrawal { client, tx, .. }
+            | Event::Fee { client, tx, .. }
+            | Event::Interest { client, tx, .. } => {
+                if !self.transactions.insert(tx, client) {
+                    self.reject(&event, Reason::DuplicateTx);
+                    return Ok(());
+                }
+            }
+            Event::Transfer { client, tx, .. } => {
+                if !self.transactions.insert(tx, client) {
+                    self.reject(&event, Reason::DuplicateTx);
+                    return Ok(());
+                }
+                return self.transfer(&event);
+            }
+            Event::Dispute { client, tx, .. }
+            | Event::Resolve { client, tx, .. }
+            | Event::Chargeback { client, tx, .. } => {
+                if matches!(self.transactions.owner(tx), Some(owner) if owner != client) {
+                    self.reject(&event, Reason::ForeignTx);
+                    return Ok(());
+                }
+                if let Some(&destination) = self.transfers.get(&tx) {
+                    return self.settle(&event, destination);
+                }
+            }
+            Event::Lock { .. } | Event::Unlock { .. } => (),
+        }
+
+        self.send(event.client(), Message::Event(event))
     }
 
-    /// Get a client or initialize a new one, if a previously not known Client ID arrives
-    fn get_or_insert(&mut self, client_id: u16) -> &mut (mpsc::Sender<Event>, JoinHandle<Client>) {
-        self.clients.entry(client_id).or_insert_with(|| {
-            let (tx, rx) = mpsc::channel::<Event>();
-            let client = Client::new(client_id, rx);
+    /// Sends a message to the worker of the given client. The clients are sharded by their id,
+    /// so the messages of a client are always handled by the same worker in order. If the queue of
+    /// the worker is full, it blocks until the worker catches up, and the time spent blocked is
+    /// measured.
+    fn send(&mut self, client: u16, message: Message) -> crate::Result<()> {
+        self.seen[client as usize / u64::BITS as usize] |=
+            1 << (client as usize % u64::BITS as usize);
+        let worker = &self.workers[client as usize % self.workers.len()];
+        self.blocked += worker
+            .send(message)
+            .map_err(|_| KrctError::Worker(client))?;
 
-            (tx, std::thread::spawn(move || client.start_handling()))
+        Ok(())
+    }
+
+    /// The client ids set in the given bitset, which are handled by the given worker
+    fn shard(seen: &[u64], shard: usize, shards: usize) -> impl Iterator<Item = u16> + '_ {
+        (0..=u16::MAX).filter(move |&client| {
+            client as usize % shards == shard
+                && seen[client as usize / u64::BITS as usize]
+                    & 1 << (client as usize % u64::BITS as usize)
+                    != 0
         })
     }
 
-    #[cfg(test)]
-    pub fn len(&self) -> usize {
-        self.clients.len()
+    /// Sends the request built by `request` to every worker, and gathers the items of the
+    /// replies sorted by the given client id, the items of a client keep their order. All the
+    /// requests are sent first, so the workers answer them in parallel. A reply is turned into
+    /// the items or the ids of the failed clients by `items`.
+    ///
+    /// # Error
+    /// If a client failed or a worker is not running anymore, with the ids of the failed clients
+    fn gather<R, T>(
+        &self,
+        request: impl Fn(mpsc::Sender<R>) -> Message,
+        items: impl Fn(R) -> Result<Vec<T>, Vec<u16>>,
+        client: impl Fn(&T) -> u16,
+    ) -> crate::Result<Vec<T>> {
+        let replies = self
+            .workers
+            .iter()
+            .map(|worker| {
+                let (reply, items) = mpsc::channel();
+                worker.send(request(reply)).ok().map(|_| items)
+            })
+            .collect::<Vec<_>>();
+
+        let mut gathered = Vec::new();
+        let mut failed = Vec::new();
+        for (shard, reply) in replies.into_iter().enumerate() {
+            match reply.and_then(|reply| reply.recv().ok()).map(&items) {
+                Some(Ok(shard_items)) => gathered.extend(shard_items),
+                Some(Err(shard_failed)) => failed.extend(shard_failed),
+                None => failed.extend(Self::shard(&self.seen, shard, self.workers.len())),
+            }
+        }
+        if !failed.is_empty() {
+            failed.sort_unstable();
+            return Err(KrctError::Failed(failed));
+        }
+        // The sort is stable, so the items of a client keep their order
+        gathered.sort_by_key(client);
+
+        Ok(gathered)
+    }
+
+    /// Reports an event rejected by the pool, if the rejections are collected
+    fn reject(&self, event: &Event, reason: Reason) {
+        if let Some((rejections, _)) = &self.rejections {
+            // The receiver is gone only if nobody is interested in the rejections anymore
+            let _ = rejections.send(Rejection::new(event, reason));
+        }
     }
 }
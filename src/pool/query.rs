@@ -0,0 +1,45 @@
+use super::worker::Message;
+use super::{Account, Pool};
+use crate::error::KrctError;
+use std::sync::mpsc;
+
+impl Pool {
+    /// Current balances of the given client without stopping the workers. The query is queued
+    /// after the already handled events of the client, so the result reflects all of them.
+    ///
+    /// Returns `None`, if the client is not known or it has failed.
+    ///
+    /// # Error
+    /// If the worker of the client is not running anymore
+    pub fn balance(&self, client: u16) -> crate::Result<Option<Account>> {
+        let worker = &self.workers[client as usize % self.workers.len()];
+        let (reply, accounts) = mpsc::channel();
+        worker
+            .send(Message::Query {
+                client: Some(client),
+                reply,
+            })
+            .map_err(|_| KrctError::Worker(client))?;
+        let accounts = accounts.recv().map_err(|_| KrctError::Worker(client))?;
+
+        Ok(accounts.into_iter().next())
+    }
+
+    /// Current balances of all the clients sorted by the client identifier, without stopping the
+    /// workers. The queries are queued after all the already handled events, and no new event
+    /// could be handled meanwhile, so the result is a consistent point-in-time view. The failed
+    /// clients are not included.
+    ///
+    /// # Error
+    /// If a worker is not running anymore, with the ids of its clients
+    pub fn snapshot(&self) -> crate::Result<Vec<Account>> {
+        self.gather(
+            |reply| Message::Query {
+                client: None,
+                reply,
+            },
+            Ok,
+            Account::id,
+        )
+    }
+}
@@ -0,0 +1,160 @@
+use crate::config::ReportFormat;
+use crate::error::KrctError;
+use crate::{Event, TransactionType};
+use std::sync::mpsc;
+
+/// Machine-readable reason, why an event was rejected
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    /// The available amount does not cover the event
+    InsufficientFunds,
+    /// The client account is locked
+    AccountLocked,
+    /// The referenced transaction is not known
+    UnknownTx,
+    /// The referenced transaction is not in the state the event requires, e.g. resolving a not
+    /// disputed transaction
+    InvalidState,
+    /// The amount of the event is not positive or it exceeds the disputed or the disputable
+    /// amount of the referenced transaction
+    InvalidAmount,
+    /// Applying the event would overflow a balance
+    Overflow,
+    /// The transaction id has been already used by a previous transaction
+    DuplicateTx,
+    /// The referenced transaction belongs to a different client
+    ForeignTx,
+    /// The destination of a transfer is missing or it is the source client itself
+    InvalidDestination,
+}
+
+/// An event rejected by the engine
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Rejection {
+    pub client: u16,
+    pub tx: u32,
+    #[serde(rename = "type")]
+    pub(crate) _type: TransactionType,
+    pub reason: Reason,
+}
+
+impl Rejection {
+    /// Rejection of the given event with the given reason
+    pub(in crate::pool) fn new(event: &Event, reason: Reason) -> Self {
+        Self {
+            client: event.client(),
+            tx: event.tx(),
+            _type: event.kind(),
+            reason,
+        }
+    }
+}
+
+/// Stream of the rejected events. The stream ends, when the engine has been dumped (or dropped),
+/// so it has to be consumed after `Krct::dump` or on a separate thread. The channel is unbounded,
+/// so on a large input it is better consumed on a separate thread while the input is read, as the
+/// binary does.
+pub struct Rejections(pub(in crate::pool) mpsc::Receiver<Rejection>);
+
+impl Iterator for Rejections {
+    type Item = Rejection;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.recv().ok()
+    }
+}
+
+impl Rejections {
+    /// Writes the rejected events to the given writer in the given format
+    pub fn dump<W: std::io::Write>(self, writer: W, format: ReportFormat) -> crate::Result<()> {
+        self.write(writer, format, true).map_err(KrctError::CsvDump)
+    }
+
+    /// Writes the rejected events to the end of an existing report in the given format, so a CSV
+    /// report gets no second header row, e.g. after `Engine::recover`
+    pub fn append<W: std::io::Write>(self, writer: W, format: ReportFormat) -> crate::Result<()> {
+        self.write(writer, format, false)
+            .map_err(KrctError::CsvDump)
+    }
+
+    fn write<W: std::io::Write>(
+        self,
+        mut writer: W,
+        format: ReportFormat,
+        headers: bool,
+    ) -> std::io::Result<()> {
+        match format {
+            ReportFormat::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(headers)
+                    .from_writer(writer);
+                for rejection in self {
+                    writer.serialize(rejection)?;
+                    writer.flush()?;
+                }
+            }
+            ReportFormat::Ndjson => {
+                for rejection in self {
+                    serde_json::to_writer(&mut writer, &rejection)?;
+                    writeln!(writer)?;
+                }
+                writer.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Reason, Rejection, Rejections};
+    use crate::config::ReportFormat;
+    use crate::TransactionType;
+
+    fn rejections() -> Rejections {
+        let (tx, rx) = std::sync::mpsc::channel();
+        tx.send(Rejection {
+            client: 1,
+            tx: 2,
+            _type: TransactionType::Withdrawal,
+            reason: Reason::InsufficientFunds,
+        })
+        .expect("Failed to send rejection");
+
+        Rejections(rx)
+    }
+
+    #[test]
+    fn test_csv_report() {
+        let mut buffer = Vec::new();
+        assert!(rejections().dump(&mut buffer, ReportFormat::Csv).is_ok());
+        assert_eq!(
+            String::from_utf8_lossy(&buffer),
+            "client,tx,type,reason\n\
+            1,2,withdrawal,insufficient_funds\n"
+        );
+    }
+
+    #[test]
+    fn test_appended_csv_report() {
+        let mut buffer = b"client,tx,type,reason\n".to_vec();
+        assert!(rejections().append(&mut buffer, ReportFormat::Csv).is_ok());
+        assert_eq!(
+            String::from_utf8_lossy(&buffer),
+            "client,tx,type,reason\n\
+            1,2,withdrawal,insufficient_funds\n"
+        );
+    }
+
+    #[test]
+    fn test_ndjson_report() {
+        let mut buffer = Vec::new();
+        assert!(rejections().dump(&mut buffer, ReportFormat::Ndjson).is_ok());
+        assert_eq!(
+            String::from_utf8_lossy(&buffer),
+            "{\"client\":1,\"tx\":2,\"type\":\"withdrawal\",\"reason\":\"insufficient_funds\"}\n"
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use super::amount::Amount;
+use super::client::{Client, LockReason};
+use super::history::{History, Record};
+use super::index::TxIndex;
+use super::rejection::Rejection;
+use super::worker::Message;
+use super::Pool;
+use crate::config::Policy;
+use crate::error::KrctError;
+use std::sync::mpsc;
+
+/// Full state of the engine, so a later run could be started from it instead of zero balances,
+/// e.g. the next daily input could dispute a deposit of an earlier day. It holds every client with
+/// its balances, lock and transaction history, the already used transaction ids and the
+/// transfers.
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct State {
+    pub(in crate::pool) clients: Vec<ClientState>,
+    /// The used transaction ids and their owner client, page by page in the compact form of the
+    /// index
+    pub(in crate::pool) transactions: TxIndex,
+    /// The transfer transaction ids and their destination client
+    pub(in crate::pool) transfers: Vec<(u32, u16)>,
+}
+
+impl State {
+    /// Reads a state previously written by `State::write`
+    ///
+    /// # Error
+    /// If the state could not be read, or it is invalid
+    pub fn read<R: std::io::Read>(reader: R) -> crate::Result<Self> {
+        serde_json::from_reader(std::io::BufReader::new(reader)).map_err(KrctError::StateRead)
+    }
+
+    /// Writes the state as JSON to the given writer
+    ///
+    /// # Error
+    /// If the state could not be written
+    pub fn write<W: std::io::Write>(&self, writer: W) -> crate::Result<()> {
+        let mut writer = std::io::BufWriter::new(writer);
+        serde_json::to_writer(&mut writer, self)
+            .map_err(|err| KrctError::StateWrite(err.into()))?;
+        std::io::Write::flush(&mut writer).map_err(KrctError::StateWrite)?;
+
+        Ok(())
+    }
+}
+
+impl Pool {
+    /// Exports the full state of the engine without stopping the workers. Like a snapshot, the
+    /// exports are queued after all the already handled events, so the state is consistent.
+    ///
+    /// # Error
+    /// If a client failed or a worker is not running anymore, with the ids of the failed clients
+    pub fn state(&self) -> crate::Result<State> {
+        let clients = self.gather(
+            |reply| Message::Export { reply },
+            |reply| reply,
+            |client| client.id,
+        )?;
+        // The pages of the index are shared with the state, not copied
+        let transactions = self.transactions.clone();
+        let mut transfers = self
+            .transfers
+            .iter()
+            .map(|(&tx, &destination)| (tx, destination))
+            .collect::<Vec<_>>();
+        transfers.sort_unstable();
+
+        Ok(State {
+            clients,
+            transactions,
+            transfers,
+        })
+    }
+
+    /// Restores the given state, the restored clients replace the current ones. It is meant to be
+    /// called before any event is handled.
+    ///
+    /// # Error
+    /// If the worker of a client is not running anymore
+    pub fn restore(&mut self, state: State) -> crate::Result<()> {
+        self.transactions.extend(state.transactions);
+        self.transfers.extend(state.transfers);
+        for client in state.clients {
+            self.send(client.id, Message::Restore(Box::new(client)))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// State of a single client
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(in crate::pool) struct ClientState {
+    #[serde(rename = "client")]
+    pub(in crate::pool) id: u16,
+    available: Amount,
+    held: Amount,
+    total: Amount,
+    locked: bool,
+    lock_reason: Option<LockReason>,
+    fees: Amount,
+    interest: Amount,
+    history: Vec<RecordState>,
+}
+
+/// A recorded transaction of a client
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct RecordState {
+    tx: u32,
+    #[serde(flatten)]
+    record: Record,
+}
+
+impl From<&Client> for ClientState {
+    fn from(client: &Client) -> Self {
+        let mut history = client
+            .transaction_history
+            .iter()
+            .map(|(&tx, record)| RecordState {
+                tx,
+                record: record.clone(),
+            })
+            .collect::<Vec<_>>();
+        history.sort_by_key(|record| record.tx);
+
+        Self {
+            id: client.id,
+            available: client.available,
+            held: client.held,
+            total: client.total,
+            locked: client.locked,
+            lock_reason: client.lock_reason,
+            fees: client.fees,
+            interest: client.interest,
+            history,
+        }
+    }
+}
+
+impl ClientState {
+    /// Restores the client, which reports to the given rejection channel and follows the given
+    /// policy. The ledger starts empty, it records only the events applied after the restore.
+    pub(in crate::pool) fn into_client(
+        self,
+        rejections: Option<mpsc::Sender<Rejection>>,
+        policy: Policy,
+        ledger: bool,
+    ) -> Client {
+        let mut client = Client::new(self.id, rejections, policy, ledger);
+        let mut history = History::default();
+        for RecordState { tx, record } in self.history {
+            history.restore(tx, Some(record));
+        }
+        client.transaction_history = history;
+        client.available = self.available;
+        client.held = self.held;
+        client.total = self.total;
+        client.locked = self.locked;
+        client.lock_reason = self.lock_reason;
+        client.fees = self.fees;
+        client.interest = self.interest;
+
+        client
+    }
+}
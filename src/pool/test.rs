@@ -1,13 +1,16 @@
 use super::amount::Amount;
-use super::client::Client;
-use super::history::{History, State};
+use super::client::{Client, LockReason};
+use super::history::{Direction, History, State};
+use super::rejection::Reason;
+use super::worker::Handle;
 use super::Pool;
-use crate::Event;
+use crate::{Config, Event, LockedPolicy, Policy};
 use std::collections::HashMap;
+use std::time::Duration;
 
 struct ClientAssertion {
     id: u16,
-    transaction_history: History<u32, f32>,
+    transaction_history: History<u32>,
     available: f64,
     held: f64,
     total: f64,
@@ -18,16 +21,38 @@ fn send(pool: &mut Pool, event: Event) {
     assert!(pool.handle(event).is_ok())
 }
 
+fn healthy(client: crate::Result<Client>) -> Client {
+    client.expect("Client should not fail")
+}
+
+fn assert_rejections(events: Vec<Event>, expected: Vec<(u16, u32, Reason)>) {
+    let mut pool = Pool::new(&Config {
+        rejections: true,
+        ..Config::default()
+    });
+    let rejections = pool.rejections().expect("Rejections should be collected");
+    for event in events {
+        send(&mut pool, event);
+    }
+    assert_eq!(pool.iter().count(), 1);
+
+    let rejections = rejections
+        .map(|rejection| (rejection.client, rejection.tx, rejection.reason))
+        .collect::<Vec<_>>();
+    assert_eq!(rejections, expected);
+}
+
 fn assert_clients(pool: Pool, expected: Vec<ClientAssertion>) {
-    assert_eq!(
-        pool.len(),
-        expected.len(),
-        "Pool length and Assertion asset length should be equal"
-    );
     let result_set = pool
         .iter()
+        .map(healthy)
         .map(|client| (client.id, client))
         .collect::<HashMap<u16, Client>>();
+    assert_eq!(
+        result_set.len(),
+        expected.len(),
+        "Pool length and Assertion asset length should be equal"
+    );
 
     for expected_client in expected {
         let client = result_set.get(&expected_client.id);
@@ -56,7 +81,7 @@ fn test_deposit_flow() {
         Event::Deposit {
             client: 1,
             tx: 1,
-            amount: 1.0,
+            amount: Amount::from(1.0),
         },
     );
     send(
@@ -64,7 +89,7 @@ fn test_deposit_flow() {
         Event::Deposit {
             client: 1,
             tx: 2,
-            amount: 2.0,
+            amount: Amount::from(2.0),
         },
     );
     send(
@@ -72,7 +97,7 @@ fn test_deposit_flow() {
         Event::Deposit {
             client: 2,
             tx: 3,
-            amount: 1.0,
+            amount: Amount::from(1.0),
         },
     );
     assert_clients(
@@ -109,7 +134,7 @@ fn test_withdrawal_flow() {
         Event::Deposit {
             client: 1,
             tx: 1,
-            amount: 2.0,
+            amount: Amount::from(2.0),
         },
     );
     send(
@@ -117,7 +142,7 @@ fn test_withdrawal_flow() {
         Event::Withdrawal {
             client: 1,
             tx: 2,
-            amount: 1.0,
+            amount: Amount::from(1.0),
         },
     );
     send(
@@ -125,7 +150,7 @@ fn test_withdrawal_flow() {
         Event::Withdrawal {
             client: 2,
             tx: 3,
-            amount: 1.0,
+            amount: Amount::from(1.0),
         },
     );
     assert_clients(
@@ -137,7 +162,10 @@ fn test_withdrawal_flow() {
                 held: 0.0,
                 total: 1.0,
                 locked: false,
-                transaction_history: History::from([(1, 2.0, State::Recorded)]),
+                transaction_history: History::from([
+                    (1, Direction::Incoming, 2.0, State::Recorded),
+                    (2, Direction::Outgoing, 1.0, State::Recorded),
+                ]),
             },
             ClientAssertion {
                 id: 2,
@@ -159,7 +187,7 @@ fn test_dispute_flow() {
         Event::Deposit {
             client: 1,
             tx: 1,
-            amount: 2.0,
+            amount: Amount::from(2.0),
         },
     );
     send(
@@ -167,10 +195,17 @@ fn test_dispute_flow() {
         Event::Deposit {
             client: 1,
             tx: 2,
-            amount: 1.0,
+            amount: Amount::from(1.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 1,
+            tx: 2,
+            amount: None,
         },
     );
-    send(&mut pool, Event::Dispute { client: 1, tx: 2 });
     assert_clients(
         pool,
         vec![ClientAssertion {
@@ -192,7 +227,7 @@ fn test_resolve_flow() {
         Event::Deposit {
             client: 1,
             tx: 1,
-            amount: 2.0,
+            amount: Amount::from(2.0),
         },
     );
     send(
@@ -200,11 +235,25 @@ fn test_resolve_flow() {
         Event::Deposit {
             client: 1,
             tx: 2,
-            amount: 1.0,
+            amount: Amount::from(1.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 1,
+            tx: 2,
+            amount: None,
+        },
+    );
+    send(
+        &mut pool,
+        Event::Resolve {
+            client: 1,
+            tx: 2,
+            amount: None,
         },
     );
-    send(&mut pool, Event::Dispute { client: 1, tx: 2 });
-    send(&mut pool, Event::Resolve { client: 1, tx: 2 });
     assert_clients(
         pool,
         vec![ClientAssertion {
@@ -229,7 +278,7 @@ fn test_chargeback_flow() {
         Event::Deposit {
             client: 1,
             tx: 1,
-            amount: 2.0,
+            amount: Amount::from(2.0),
         },
     );
     send(
@@ -237,11 +286,25 @@ fn test_chargeback_flow() {
         Event::Deposit {
             client: 1,
             tx: 2,
-            amount: 1.0,
+            amount: Amount::from(1.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 1,
+            tx: 2,
+            amount: None,
+        },
+    );
+    send(
+        &mut pool,
+        Event::Chargeback {
+            client: 1,
+            tx: 2,
+            amount: None,
         },
     );
-    send(&mut pool, Event::Dispute { client: 1, tx: 2 });
-    send(&mut pool, Event::Chargeback { client: 1, tx: 2 });
     assert_clients(
         pool,
         vec![ClientAssertion {
@@ -266,17 +329,31 @@ fn test_deposit_after_chargeback() {
         Event::Deposit {
             client: 1,
             tx: 1,
-            amount: 2.0,
+            amount: Amount::from(2.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 1,
+            tx: 1,
+            amount: None,
+        },
+    );
+    send(
+        &mut pool,
+        Event::Chargeback {
+            client: 1,
+            tx: 1,
+            amount: None,
         },
     );
-    send(&mut pool, Event::Dispute { client: 1, tx: 1 });
-    send(&mut pool, Event::Chargeback { client: 1, tx: 1 });
     send(
         &mut pool,
         Event::Deposit {
             client: 1,
             tx: 2,
-            amount: 1.0,
+            amount: Amount::from(1.0),
         },
     );
     assert_clients(
@@ -300,10 +377,17 @@ fn test_dispute_event_ignored_upon_non_existent_transaction_id() {
         Event::Deposit {
             client: 1,
             tx: 1,
-            amount: 1.0,
+            amount: Amount::from(1.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 1,
+            tx: 2,
+            amount: None,
         },
     );
-    send(&mut pool, Event::Dispute { client: 1, tx: 2 });
     assert_clients(
         pool,
         vec![ClientAssertion {
@@ -325,11 +409,25 @@ fn test_resolve_event_ignored_upon_non_existent_transaction_id() {
         Event::Deposit {
             client: 1,
             tx: 1,
-            amount: 1.0,
+            amount: Amount::from(1.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 1,
+            tx: 1,
+            amount: None,
+        },
+    );
+    send(
+        &mut pool,
+        Event::Resolve {
+            client: 1,
+            tx: 2,
+            amount: None,
         },
     );
-    send(&mut pool, Event::Dispute { client: 1, tx: 1 });
-    send(&mut pool, Event::Resolve { client: 1, tx: 2 });
     assert_clients(
         pool,
         vec![ClientAssertion {
@@ -351,10 +449,17 @@ fn test_resolve_event_ignored_upon_non_disputed_transaction_id() {
         Event::Deposit {
             client: 1,
             tx: 1,
-            amount: 1.0,
+            amount: Amount::from(1.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Resolve {
+            client: 1,
+            tx: 1,
+            amount: None,
         },
     );
-    send(&mut pool, Event::Resolve { client: 1, tx: 1 });
     assert_clients(
         pool,
         vec![ClientAssertion {
@@ -376,11 +481,25 @@ fn test_chargeback_event_ignored_upon_non_existent_transaction_id() {
         Event::Deposit {
             client: 1,
             tx: 1,
-            amount: 1.0,
+            amount: Amount::from(1.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 1,
+            tx: 1,
+            amount: None,
+        },
+    );
+    send(
+        &mut pool,
+        Event::Chargeback {
+            client: 1,
+            tx: 2,
+            amount: None,
         },
     );
-    send(&mut pool, Event::Dispute { client: 1, tx: 1 });
-    send(&mut pool, Event::Chargeback { client: 1, tx: 2 });
     assert_clients(
         pool,
         vec![ClientAssertion {
@@ -402,10 +521,17 @@ fn test_chargeback_event_ignored_upon_non_disputed_transaction_id() {
         Event::Deposit {
             client: 1,
             tx: 1,
-            amount: 1.0,
+            amount: Amount::from(1.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Chargeback {
+            client: 1,
+            tx: 1,
+            amount: None,
         },
     );
-    send(&mut pool, Event::Chargeback { client: 1, tx: 1 });
     assert_clients(
         pool,
         vec![ClientAssertion {
@@ -420,23 +546,257 @@ fn test_chargeback_event_ignored_upon_non_disputed_transaction_id() {
 }
 
 #[test]
-fn test_unknown_event_is_ignored() {
+fn test_rejections_are_not_collected_by_default() {
+    let mut pool = Pool::default();
+    assert!(pool.rejections().is_none());
+}
+
+#[test]
+fn test_withdrawal_rejected_upon_insufficient_funds() {
+    assert_rejections(
+        vec![
+            Event::Deposit {
+                client: 1,
+                tx: 1,
+                amount: Amount::from(1.0),
+            },
+            Event::Withdrawal {
+                client: 1,
+                tx: 2,
+                amount: Amount::from(1.5),
+            },
+        ],
+        vec![(1, 2, Reason::InsufficientFunds)],
+    );
+}
+
+#[test]
+fn test_deposit_and_withdrawal_rejected_upon_non_positive_amount() {
+    assert_rejections(
+        vec![
+            Event::Deposit {
+                client: 1,
+                tx: 1,
+                amount: Amount::from(-10.0),
+            },
+            Event::Deposit {
+                client: 1,
+                tx: 2,
+                amount: Amount::ZERO,
+            },
+            Event::Deposit {
+                client: 1,
+                tx: 3,
+                amount: Amount::from(1.0),
+            },
+            Event::Withdrawal {
+                client: 1,
+                tx: 4,
+                amount: Amount::from(-5.0),
+            },
+            Event::Withdrawal {
+                client: 1,
+                tx: 5,
+                amount: Amount::ZERO,
+            },
+        ],
+        vec![
+            (1, 1, Reason::InvalidAmount),
+            (1, 2, Reason::InvalidAmount),
+            (1, 4, Reason::InvalidAmount),
+            (1, 5, Reason::InvalidAmount),
+        ],
+    );
+}
+
+#[test]
+fn test_non_positive_amount_does_not_change_balances() {
+    let mut pool = Pool::default();
+    send(&mut pool, Event::deposit(1, 1, Amount::from(2.0)));
+    send(&mut pool, Event::deposit(1, 2, Amount::from(-10.0)));
+    send(&mut pool, Event::withdrawal(1, 3, Amount::from(-5.0)));
+
+    let client = healthy(pool.iter().next().expect("Client should exist"));
+    assert_eq!(client.available, Amount::from(2.0));
+    assert_eq!(client.total, Amount::from(2.0));
+}
+
+#[test]
+fn test_events_rejected_upon_locked_account() {
+    assert_rejections(
+        vec![
+            Event::Deposit {
+                client: 1,
+                tx: 1,
+                amount: Amount::from(1.0),
+            },
+            Event::Dispute {
+                client: 1,
+                tx: 1,
+                amount: None,
+            },
+            Event::Chargeback {
+                client: 1,
+                tx: 1,
+                amount: None,
+            },
+            Event::Deposit {
+                client: 1,
+                tx: 2,
+                amount: Amount::from(1.0),
+            },
+            Event::Dispute {
+                client: 1,
+                tx: 2,
+                amount: None,
+            },
+        ],
+        vec![(1, 2, Reason::AccountLocked), (1, 2, Reason::AccountLocked)],
+    );
+}
+
+#[test]
+fn test_dispute_rejected_upon_unknown_transaction() {
+    assert_rejections(
+        vec![Event::Dispute {
+            client: 1,
+            tx: 1,
+            amount: None,
+        }],
+        vec![(1, 1, Reason::UnknownTx)],
+    );
+}
+
+#[test]
+fn test_resolve_rejected_upon_invalid_state() {
+    assert_rejections(
+        vec![
+            Event::Deposit {
+                client: 1,
+                tx: 1,
+                amount: Amount::from(1.0),
+            },
+            Event::Resolve {
+                client: 1,
+                tx: 1,
+                amount: None,
+            },
+            Event::Chargeback {
+                client: 1,
+                tx: 1,
+                amount: None,
+            },
+        ],
+        vec![(1, 1, Reason::InvalidState), (1, 1, Reason::InvalidState)],
+    );
+}
+
+#[test]
+fn test_duplicate_transaction_ids_are_rejected() {
+    assert_rejections(
+        vec![
+            Event::Deposit {
+                client: 1,
+                tx: 1,
+                amount: Amount::from(2.0),
+            },
+            Event::Dispute {
+                client: 1,
+                tx: 1,
+                amount: None,
+            },
+            Event::Deposit {
+                client: 1,
+                tx: 1,
+                amount: Amount::from(3.0),
+            },
+            Event::Withdrawal {
+                client: 1,
+                tx: 1,
+                amount: Amount::from(1.0),
+            },
+        ],
+        vec![(1, 1, Reason::DuplicateTx), (1, 1, Reason::DuplicateTx)],
+    );
+}
+
+#[test]
+fn test_duplicate_transaction_ids_across_clients() {
     let mut pool = Pool::default();
     send(
         &mut pool,
         Event::Deposit {
             client: 1,
             tx: 1,
-            amount: 1.0,
+            amount: Amount::from(2.0),
         },
     );
-    send(&mut pool, Event::Unknown);
     send(
         &mut pool,
         Event::Deposit {
+            client: 2,
+            tx: 1,
+            amount: Amount::from(3.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
             client: 1,
-            tx: 3,
-            amount: 1.0,
+            tx: 1,
+            amount: None,
+        },
+    );
+    assert_clients(
+        pool,
+        vec![ClientAssertion {
+            id: 1,
+            available: 0.0,
+            held: 2.0,
+            total: 2.0,
+            locked: false,
+            transaction_history: History::from([(1, 2.0, State::Held)]),
+        }],
+    );
+}
+
+#[test]
+fn test_dispute_of_another_clients_transaction_is_rejected() {
+    let mut pool = Pool::new(&Config {
+        rejections: true,
+        ..Config::default()
+    });
+    let rejections = pool.rejections().expect("Rejections should be collected");
+    send(
+        &mut pool,
+        Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(2.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 2,
+            tx: 1,
+            amount: None,
+        },
+    );
+    send(
+        &mut pool,
+        Event::Resolve {
+            client: 2,
+            tx: 1,
+            amount: None,
+        },
+    );
+    send(
+        &mut pool,
+        Event::Chargeback {
+            client: 2,
+            tx: 1,
+            amount: None,
         },
     );
     assert_clients(
@@ -447,10 +807,973 @@ fn test_unknown_event_is_ignored() {
             held: 0.0,
             total: 2.0,
             locked: false,
+            transaction_history: History::from([(1, 2.0, State::Recorded)]),
+        }],
+    );
+    assert_eq!(
+        rejections
+            .map(|rejection| (rejection.client, rejection.tx, rejection.reason))
+            .collect::<Vec<_>>(),
+        vec![
+            (2, 1, Reason::ForeignTx),
+            (2, 1, Reason::ForeignTx),
+            (2, 1, Reason::ForeignTx)
+        ]
+    );
+}
+
+#[test]
+fn test_withdrawal_dispute_flow() {
+    let mut pool = Pool::default();
+    send(
+        &mut pool,
+        Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(3.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Withdrawal {
+            client: 1,
+            tx: 2,
+            amount: Amount::from(1.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 1,
+            tx: 2,
+            amount: None,
+        },
+    );
+    assert_clients(
+        pool,
+        vec![ClientAssertion {
+            id: 1,
+            available: 2.0,
+            held: 1.0,
+            total: 3.0,
+            locked: false,
             transaction_history: History::from([
-                (1, 1.0, State::Recorded),
-                (3, 1.0, State::Recorded),
+                (1, Direction::Incoming, 3.0, State::Recorded),
+                (2, Direction::Outgoing, 1.0, State::Held),
             ]),
         }],
     );
 }
+
+#[test]
+fn test_withdrawal_resolve_flow() {
+    let mut pool = Pool::default();
+    send(
+        &mut pool,
+        Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(3.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Withdrawal {
+            client: 1,
+            tx: 2,
+            amount: Amount::from(1.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 1,
+            tx: 2,
+            amount: None,
+        },
+    );
+    send(
+        &mut pool,
+        Event::Resolve {
+            client: 1,
+            tx: 2,
+            amount: None,
+        },
+    );
+    assert_clients(
+        pool,
+        vec![ClientAssertion {
+            id: 1,
+            available: 2.0,
+            held: 0.0,
+            total: 2.0,
+            locked: false,
+            transaction_history: History::from([
+                (1, Direction::Incoming, 3.0, State::Recorded),
+                (2, Direction::Outgoing, 1.0, State::Recorded),
+            ]),
+        }],
+    );
+}
+
+#[test]
+fn test_withdrawal_chargeback_flow() {
+    let mut pool = Pool::default();
+    send(
+        &mut pool,
+        Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(3.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Withdrawal {
+            client: 1,
+            tx: 2,
+            amount: Amount::from(1.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 1,
+            tx: 2,
+            amount: None,
+        },
+    );
+    send(
+        &mut pool,
+        Event::Chargeback {
+            client: 1,
+            tx: 2,
+            amount: None,
+        },
+    );
+    assert_clients(
+        pool,
+        vec![ClientAssertion {
+            id: 1,
+            available: 3.0,
+            held: 0.0,
+            total: 3.0,
+            locked: false,
+            transaction_history: History::from([
+                (1, Direction::Incoming, 3.0, State::Recorded),
+                (2, Direction::Outgoing, 1.0, State::ChargedBack),
+            ]),
+        }],
+    );
+}
+
+#[test]
+fn test_partial_dispute_flow() {
+    let mut pool = Pool::new(&Config {
+        rejections: true,
+        ..Config::default()
+    });
+    let rejections = pool.rejections().expect("Rejections should be collected");
+    send(
+        &mut pool,
+        Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(10.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 1,
+            tx: 1,
+            amount: Some(Amount::from(4.0)),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 1,
+            tx: 1,
+            amount: Some(Amount::from(7.0)),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Resolve {
+            client: 1,
+            tx: 1,
+            amount: Some(Amount::from(1.0)),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Chargeback {
+            client: 1,
+            tx: 1,
+            amount: Some(Amount::from(5.0)),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Chargeback {
+            client: 1,
+            tx: 1,
+            amount: Some(Amount::from(2.0)),
+        },
+    );
+    let mut transaction_history = History::default();
+    transaction_history.insert(1, Amount::from(10.0), Direction::Incoming);
+    transaction_history.hold(&1, Amount::from(3.0));
+    transaction_history.reverse(&1, Amount::from(2.0));
+    assert_clients(
+        pool,
+        vec![ClientAssertion {
+            id: 1,
+            available: 7.0,
+            held: 1.0,
+            total: 8.0,
+            locked: true,
+            transaction_history,
+        }],
+    );
+    assert_eq!(
+        rejections
+            .map(|rejection| (rejection.client, rejection.tx, rejection.reason))
+            .collect::<Vec<_>>(),
+        vec![(1, 1, Reason::InvalidAmount), (1, 1, Reason::InvalidAmount)]
+    );
+}
+
+#[test]
+fn test_remaining_amount_disputed_without_amount() {
+    let mut pool = Pool::default();
+    send(
+        &mut pool,
+        Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(10.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 1,
+            tx: 1,
+            amount: Some(Amount::from(4.0)),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 1,
+            tx: 1,
+            amount: None,
+        },
+    );
+    assert_clients(
+        pool,
+        vec![ClientAssertion {
+            id: 1,
+            available: 0.0,
+            held: 10.0,
+            total: 10.0,
+            locked: false,
+            transaction_history: History::from([(1, 10.0, State::Held)]),
+        }],
+    );
+}
+
+#[test]
+fn test_lock_reasons() {
+    let mut pool = Pool::default();
+    send(
+        &mut pool,
+        Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(1.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Dispute {
+            client: 1,
+            tx: 1,
+            amount: None,
+        },
+    );
+    send(
+        &mut pool,
+        Event::Chargeback {
+            client: 1,
+            tx: 1,
+            amount: None,
+        },
+    );
+    send(&mut pool, Event::Lock { client: 2, tx: 2 });
+    send(&mut pool, Event::Lock { client: 3, tx: 3 });
+    send(&mut pool, Event::Unlock { client: 3, tx: 4 });
+
+    let clients = pool
+        .sorted()
+        .map(healthy)
+        .map(|client| (client.id, client.locked, client.lock_reason))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        clients,
+        vec![
+            (1, true, Some(LockReason::Chargeback)),
+            (2, true, Some(LockReason::Manual)),
+            (3, false, None),
+        ]
+    );
+}
+
+#[test]
+fn test_chargeback_keeps_manual_lock_reason() {
+    let mut pool = Pool::new(&Config {
+        policy: Policy {
+            locked: LockedPolicy {
+                chargeback: true,
+                ..LockedPolicy::default()
+            },
+            ..Policy::default()
+        },
+        ..Config::default()
+    });
+    for event in [
+        Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(1.0),
+        },
+        Event::Dispute {
+            client: 1,
+            tx: 1,
+            amount: None,
+        },
+        Event::Lock { client: 1, tx: 2 },
+        Event::Chargeback {
+            client: 1,
+            tx: 1,
+            amount: None,
+        },
+    ] {
+        send(&mut pool, event);
+    }
+
+    let clients = pool
+        .sorted()
+        .map(healthy)
+        .map(|client| (client.id, client.total, client.locked, client.lock_reason))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        clients,
+        vec![(1, Amount::from(0.0), true, Some(LockReason::Manual))]
+    );
+}
+
+#[test]
+fn test_locked_policy_allows_settling_disputes() {
+    let mut pool = Pool::new(&Config {
+        rejections: true,
+        policy: Policy {
+            locked: LockedPolicy {
+                deposit: true,
+                resolve: true,
+                ..LockedPolicy::default()
+            },
+            ..Policy::default()
+        },
+        ..Config::default()
+    });
+    let rejections = pool.rejections().expect("Rejections should be collected");
+    for event in [
+        Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(1.0),
+        },
+        Event::Deposit {
+            client: 1,
+            tx: 2,
+            amount: Amount::from(2.0),
+        },
+        Event::Dispute {
+            client: 1,
+            tx: 2,
+            amount: None,
+        },
+        Event::Lock { client: 1, tx: 3 },
+        Event::Resolve {
+            client: 1,
+            tx: 2,
+            amount: None,
+        },
+        Event::Deposit {
+            client: 1,
+            tx: 4,
+            amount: Amount::from(1.0),
+        },
+        Event::Withdrawal {
+            client: 1,
+            tx: 5,
+            amount: Amount::from(1.0),
+        },
+    ] {
+        send(&mut pool, event);
+    }
+
+    assert_clients(
+        pool,
+        vec![ClientAssertion {
+            id: 1,
+            transaction_history: History::from([
+                (1, 1.0, State::Recorded),
+                (2, 2.0, State::Recorded),
+                (4, 1.0, State::Recorded),
+            ]),
+            available: 4.0,
+            held: 0.0,
+            total: 4.0,
+            locked: true,
+        }],
+    );
+    let rejections = rejections
+        .map(|rejection| (rejection.client, rejection.tx, rejection.reason))
+        .collect::<Vec<_>>();
+    assert_eq!(rejections, vec![(1, 5, Reason::AccountLocked)]);
+}
+
+#[test]
+fn test_transfer_rejected_upon_non_positive_amount() {
+    // Neither side is handled, so the destination client is not even created
+    assert_rejections(
+        vec![
+            Event::deposit(1, 1, Amount::from(5.0)),
+            Event::transfer(1, 2, 2, Amount::from(-5.0)),
+            Event::transfer(1, 3, 2, Amount::ZERO),
+        ],
+        vec![(1, 2, Reason::InvalidAmount), (1, 3, Reason::InvalidAmount)],
+    );
+}
+
+#[test]
+fn test_transfer_flow() {
+    let mut pool = Pool::default();
+    send(
+        &mut pool,
+        Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(3.0),
+        },
+    );
+    send(
+        &mut pool,
+        Event::Transfer {
+            client: 1,
+            tx: 2,
+            destination: Some(2),
+            amount: Amount::from(2.0),
+        },
+    );
+
+    assert_clients(
+        pool,
+        vec![
+            ClientAssertion {
+                id: 1,
+                transaction_history: History::from([
+                    (1, Direction::Incoming, 3.0, State::Recorded),
+                    (2, Direction::Outgoing, 2.0, State::Recorded),
+                ]),
+                available: 1.0,
+                held: 0.0,
+                total: 1.0,
+                locked: false,
+            },
+            ClientAssertion {
+                id: 2,
+                transaction_history: History::from([(2, 2.0, State::Recorded)]),
+                available: 2.0,
+                held: 0.0,
+                total: 2.0,
+                locked: false,
+            },
+        ],
+    );
+}
+
+#[test]
+fn test_transfer_rolled_back_upon_locked_destination() {
+    let mut pool = Pool::new(&Config {
+        rejections: true,
+        ..Config::default()
+    });
+    let rejections = pool.rejections().expect("Rejections should be collected");
+    for event in [
+        Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(3.0),
+        },
+        Event::Lock { client: 2, tx: 2 },
+        Event::Transfer {
+            client: 1,
+            tx: 3,
+            destination: Some(2),
+            amount: Amount::from(2.0),
+        },
+        Event::Transfer {
+            client: 1,
+            tx: 4,
+            destination: Some(3),
+            amount: Amount::from(5.0),
+        },
+        Event::Transfer {
+            client: 1,
+            tx: 5,
+            destination: None,
+            amount: Amount::from(1.0),
+        },
+    ] {
+        send(&mut pool, event);
+    }
+
+    assert_clients(
+        pool,
+        vec![
+            ClientAssertion {
+                id: 1,
+                transaction_history: History::from([(1, 3.0, State::Recorded)]),
+                available: 3.0,
+                held: 0.0,
+                total: 3.0,
+                locked: false,
+            },
+            ClientAssertion {
+                id: 2,
+                transaction_history: History::default(),
+                available: 0.0,
+                held: 0.0,
+                total: 0.0,
+                locked: true,
+            },
+        ],
+    );
+    let rejections = rejections
+        .map(|rejection| (rejection.client, rejection.tx, rejection.reason))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rejections,
+        vec![
+            (1, 3, Reason::AccountLocked),
+            (1, 4, Reason::InsufficientFunds),
+            (1, 5, Reason::InvalidDestination),
+        ]
+    );
+}
+
+#[test]
+fn test_transfer_chargeback_flow() {
+    let mut pool = Pool::default();
+    for event in [
+        Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(3.0),
+        },
+        Event::Transfer {
+            client: 1,
+            tx: 2,
+            destination: Some(2),
+            amount: Amount::from(2.0),
+        },
+        Event::Dispute {
+            client: 1,
+            tx: 2,
+            amount: None,
+        },
+        Event::Chargeback {
+            client: 1,
+            tx: 2,
+            amount: None,
+        },
+    ] {
+        send(&mut pool, event);
+    }
+
+    assert_clients(
+        pool,
+        vec![
+            ClientAssertion {
+                id: 1,
+                transaction_history: History::from([
+                    (1, Direction::Incoming, 3.0, State::Recorded),
+                    (2, Direction::Outgoing, 2.0, State::ChargedBack),
+                ]),
+                available: 3.0,
+                held: 0.0,
+                total: 3.0,
+                locked: false,
+            },
+            ClientAssertion {
+                id: 2,
+                transaction_history: History::from([(2, 2.0, State::ChargedBack)]),
+                available: 0.0,
+                held: 0.0,
+                total: 0.0,
+                locked: true,
+            },
+        ],
+    );
+}
+
+#[test]
+fn test_fee_and_interest_rejected_upon_non_positive_amount() {
+    assert_rejections(
+        vec![
+            Event::deposit(1, 1, Amount::from(10.0)),
+            Event::fee(1, 2, Amount::from(-50.0)),
+            Event::fee(1, 3, Amount::ZERO),
+            Event::interest(1, 4, Amount::from(-5.0)),
+            Event::interest(1, 5, Amount::ZERO),
+        ],
+        vec![
+            (1, 2, Reason::InvalidAmount),
+            (1, 3, Reason::InvalidAmount),
+            (1, 4, Reason::InvalidAmount),
+            (1, 5, Reason::InvalidAmount),
+        ],
+    );
+}
+
+#[test]
+fn test_fee_and_interest_flow() {
+    let mut pool = Pool::new(&Config {
+        rejections: true,
+        ..Config::default()
+    });
+    let rejections = pool.rejections().expect("Rejections should be collected");
+    for event in [
+        Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(1.0),
+        },
+        Event::Interest {
+            client: 1,
+            tx: 2,
+            amount: Amount::from(0.25),
+        },
+        Event::Fee {
+            client: 1,
+            tx: 3,
+            amount: Amount::from(2.0),
+        },
+        Event::Dispute {
+            client: 1,
+            tx: 2,
+            amount: None,
+        },
+        Event::Dispute {
+            client: 1,
+            tx: 3,
+            amount: None,
+        },
+    ] {
+        send(&mut pool, event);
+    }
+
+    let clients = pool
+        .sorted()
+        .map(healthy)
+        .map(|client| (client.available, client.total, client.fees, client.interest))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        clients,
+        vec![(
+            Amount::from(-0.75),
+            Amount::from(-0.75),
+            Amount::from(2.0),
+            Amount::from(0.25)
+        )]
+    );
+    let rejections = rejections
+        .map(|rejection| (rejection.client, rejection.tx, rejection.reason))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rejections,
+        vec![(1, 2, Reason::UnknownTx), (1, 3, Reason::UnknownTx)]
+    );
+}
+
+#[test]
+fn test_all_clients_are_sharded_onto_workers() {
+    let mut pool = Pool::new(&Config {
+        workers: Some(4),
+        ..Config::default()
+    });
+    for client in 0..=u16::MAX {
+        send(
+            &mut pool,
+            Event::Deposit {
+                client,
+                tx: client as u32 * 2,
+                amount: Amount::from(2.0),
+            },
+        );
+        send(
+            &mut pool,
+            Event::Withdrawal {
+                client,
+                tx: client as u32 * 2 + 1,
+                amount: Amount::from(1.5),
+            },
+        );
+    }
+
+    let clients = pool.sorted().map(healthy).collect::<Vec<_>>();
+    assert_eq!(clients.len(), u16::MAX as usize + 1);
+    assert!(clients
+        .iter()
+        .enumerate()
+        .all(|(id, client)| client.id as usize == id && client.total == Amount::from(0.5)));
+}
+
+#[test]
+fn test_transfer_across_workers() {
+    let mut pool = Pool::new(&Config {
+        workers: Some(2),
+        ..Config::default()
+    });
+    for event in [
+        Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(3.0),
+        },
+        Event::Transfer {
+            client: 1,
+            tx: 2,
+            destination: Some(2),
+            amount: Amount::from(2.0),
+        },
+        Event::Transfer {
+            client: 2,
+            tx: 3,
+            destination: Some(3),
+            amount: Amount::from(3.0),
+        },
+        Event::Transfer {
+            client: 2,
+            tx: 4,
+            destination: Some(4),
+            amount: Amount::from(1.5),
+        },
+    ] {
+        send(&mut pool, event);
+    }
+
+    let clients = pool
+        .sorted()
+        .map(healthy)
+        .map(|client| (client.id, client.total))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        clients,
+        vec![
+            (1, Amount::from(1.0)),
+            (2, Amount::from(0.5)),
+            (4, Amount::from(1.5))
+        ]
+    );
+}
+
+#[test]
+fn test_bounded_queue_keeps_every_event() {
+    let config = Config {
+        workers: Some(1),
+        queue_capacity: Some(1),
+        ..Config::default()
+    };
+    let mut pool = Pool::with_workers(&config, |worker, capacity| {
+        let (tx, rx) = std::sync::mpsc::sync_channel(capacity);
+        // The worker is held busy at first, so the reader has to wait for it
+        let worker = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            worker.start_handling(rx)
+        });
+        Handle::Thread(tx, worker)
+    });
+    for tx in 0..1000 {
+        send(
+            &mut pool,
+            Event::Deposit {
+                client: (tx % 3) as u16,
+                tx,
+                amount: Amount::from(1.0),
+            },
+        );
+    }
+    assert!(pool.blocked() > Duration::ZERO);
+
+    let clients = pool
+        .sorted()
+        .map(healthy)
+        .map(|client| (client.id, client.total))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        clients,
+        vec![
+            (0, Amount::from(334.0)),
+            (1, Amount::from(333.0)),
+            (2, Amount::from(333.0))
+        ]
+    );
+}
+
+#[test]
+fn test_querying_balances_while_processing() {
+    let mut pool = Pool::new(&Config {
+        workers: Some(2),
+        queue_capacity: Some(1),
+        ..Config::default()
+    });
+    for tx in 0..100 {
+        send(
+            &mut pool,
+            Event::Deposit {
+                client: (tx % 3) as u16,
+                tx,
+                amount: Amount::from(1.0),
+            },
+        );
+    }
+
+    let balance = pool.balance(1).expect("Balance should be queried");
+    assert_eq!(
+        balance.map(|account| account.total()),
+        Some(Amount::from(33.0))
+    );
+    assert_eq!(pool.balance(3).expect("Balance should be queried"), None);
+    let snapshot = pool
+        .snapshot()
+        .expect("Snapshot should be taken")
+        .into_iter()
+        .map(|account| (account.id(), account.total()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        snapshot,
+        vec![
+            (0, Amount::from(34.0)),
+            (1, Amount::from(33.0)),
+            (2, Amount::from(33.0))
+        ]
+    );
+
+    send(
+        &mut pool,
+        Event::Withdrawal {
+            client: 0,
+            tx: 100,
+            amount: Amount::from(4.0),
+        },
+    );
+    let balance = pool.balance(0).expect("Balance should be queried");
+    assert_eq!(
+        balance.map(|account| account.available()),
+        Some(Amount::from(30.0))
+    );
+    assert_eq!(pool.sorted().count(), 3);
+}
+
+#[test]
+fn test_continuing_from_exported_state() {
+    let mut pool = Pool::default();
+    for event in [
+        Event::Deposit {
+            client: 1,
+            tx: 1,
+            amount: Amount::from(2.0),
+        },
+        Event::Transfer {
+            client: 1,
+            tx: 2,
+            destination: Some(2),
+            amount: Amount::from(0.5),
+        },
+        Event::Fee {
+            client: 1,
+            tx: 3,
+            amount: Amount::from(0.25),
+        },
+        Event::Lock { client: 3, tx: 0 },
+    ] {
+        send(&mut pool, event);
+    }
+    let state = pool.state().expect("State should be exported");
+    let mut buffer = Vec::new();
+    assert!(state.write(&mut buffer).is_ok());
+    let restored = super::State::read(buffer.as_slice()).expect("State should be read");
+    assert_eq!(restored, state);
+    assert_eq!(pool.sorted().count(), 3);
+
+    let mut pool = Pool::new(&Config {
+        rejections: true,
+        ..Config::default()
+    });
+    let rejections = pool.rejections().expect("Rejections should be collected");
+    assert!(pool.restore(restored).is_ok());
+    for event in [
+        Event::Deposit {
+            client: 2,
+            tx: 3,
+            amount: Amount::from(1.0),
+        },
+        Event::Dispute {
+            client: 1,
+            tx: 1,
+            amount: None,
+        },
+        Event::Chargeback {
+            client: 1,
+            tx: 2,
+            amount: None,
+        },
+        Event::Dispute {
+            client: 1,
+            tx: 2,
+            amount: None,
+        },
+    ] {
+        send(&mut pool, event);
+    }
+    assert_eq!(
+        pool.state().ok().map(|state| state.transfers),
+        Some(vec![(2, 2)])
+    );
+
+    let clients = pool
+        .sorted()
+        .map(healthy)
+        .map(|client| (client.id, client.available, client.held, client.locked))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        clients,
+        vec![
+            (1, Amount::from(-0.75), Amount::from(2.5), false),
+            (2, Amount::ZERO, Amount::from(0.5), false),
+            (3, Amount::ZERO, Amount::ZERO, true)
+        ]
+    );
+    assert_eq!(
+        rejections
+            .map(|rejection| (rejection.client, rejection.tx, rejection.reason))
+            .collect::<Vec<_>>(),
+        vec![(2, 3, Reason::DuplicateTx), (1, 2, Reason::InvalidState)]
+    );
+}
@@ -0,0 +1,132 @@
+use super::worker::Message;
+use super::{Amount, Event, Pool, Reason};
+use crate::error::KrctError;
+use std::sync::mpsc;
+
+/// Constructor of a dispute, resolve or chargeback event of a client
+type Settlement = fn(u16, u32, Option<Amount>) -> Event;
+
+impl Pool {
+    /// Moves the amount of a transfer from the source client to the destination client. The
+    /// source side is handled as a withdrawal and the destination side as a deposit, and either
+    /// both of them are applied or none of them. A transfer of a non-positive amount is rejected
+    /// before any side is handled.
+    pub(in crate::pool) fn transfer(&mut self, event: &Event) -> crate::Result<()> {
+        let (source, tx, destination, amount) = match *event {
+            Event::Transfer {
+                client,
+                tx,
+                destination: Some(destination),
+                amount,
+            } if destination != client => (client, tx, destination, amount),
+            _ => {
+                self.reject(event, Reason::InvalidDestination);
+                return Ok(());
+            }
+        };
+        if amount <= Amount::ZERO {
+            self.reject(event, Reason::InvalidAmount);
+            return Ok(());
+        }
+
+        let withdrawal = Event::Withdrawal {
+            client: source,
+            tx,
+            amount,
+        };
+        let deposit = |amount| Event::Deposit {
+            client: destination,
+            tx,
+            amount,
+        };
+        if self.atomic(event, withdrawal, destination, deposit)? {
+            self.transfers.insert(tx, destination);
+        }
+
+        Ok(())
+    }
+
+    /// Disputes, resolves or charges back a transfer on both sides. The destination side is
+    /// handled first, as the negative balance policy could limit the disputed amount, then the
+    /// same amount is applied on the source side.
+    pub(in crate::pool) fn settle(&mut self, event: &Event, destination: u16) -> crate::Result<()> {
+        // The same kind of event is applied on each side
+        let (source, tx, requested, leg): (_, _, _, Settlement) = match *event {
+            Event::Dispute { client, tx, amount } => (client, tx, amount, Event::dispute),
+            Event::Resolve { client, tx, amount } => (client, tx, amount, Event::resolve),
+            Event::Chargeback { client, tx, amount } => (client, tx, amount, Event::chargeback),
+            _ => return Ok(()),
+        };
+
+        let first = leg(destination, tx, requested);
+        self.atomic(event, first, source, |amount| leg(source, tx, Some(amount)))?;
+
+        Ok(())
+    }
+
+    /// Applies the first leg, then the second one built from the amount affected by the first.
+    /// Both legs are committed, if both of them are applied, otherwise the applied leg is rolled
+    /// back and the event is rejected with the reason of the failing leg. The other client is the
+    /// client of the second leg.
+    ///
+    /// Returns whether the legs are committed.
+    fn atomic<F>(
+        &mut self,
+        event: &Event,
+        first: Event,
+        other: u16,
+        second: F,
+    ) -> crate::Result<bool>
+    where
+        F: FnOnce(Amount) -> Event,
+    {
+        let client = first.client();
+        let (first, amount) = match self.apply(first, other)? {
+            Ok(applied) => applied,
+            Err(reason) => {
+                self.reject(event, reason);
+                return Ok(false);
+            }
+        };
+        let committed = match self.apply(second(amount), client)? {
+            Ok((second, _)) => {
+                self.decide(second, true)?;
+                true
+            }
+            Err(reason) => {
+                self.reject(event, reason);
+                false
+            }
+        };
+        self.decide(first, committed)?;
+
+        Ok(committed)
+    }
+
+    /// Sends a leg to its client and waits for the result. An applied leg is pending until the
+    /// decision is sent to the client. The counterparty is the client of the other leg.
+    ///
+    /// Returns the client and the affected amount of an applied leg.
+    fn apply(
+        &mut self,
+        event: Event,
+        counterparty: u16,
+    ) -> crate::Result<Result<(u16, Amount), Reason>> {
+        let client = event.client();
+        let (reply, result) = mpsc::channel();
+        let leg = Message::Leg {
+            event,
+            counterparty,
+            reply,
+        };
+        self.send(client, leg)?;
+        let result = result.recv().map_err(|_| KrctError::Worker(client))?;
+
+        Ok(result.map(|amount| (client, amount)))
+    }
+
+    /// Commits or rolls back the pending leg of the given client
+    fn decide(&mut self, client: u16, commit: bool) -> crate::Result<()> {
+        self.send(client, Message::Decision { client, commit })
+    }
+}
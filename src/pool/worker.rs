@@ -0,0 +1,371 @@
+use super::account::Account;
+use super::amount::Amount;
+use super::client::Client;
+use super::ledger::LedgerEntry;
+use super::rejection::{Reason, Rejection};
+use super::state::ClientState;
+use crate::config::Policy;
+use crate::pool::Event;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Message received by a worker thread, each of them addressed to a single client
+#[derive(Debug)]
+pub(crate) enum Message {
+    /// An event to be handled by the client
+    Event(Event),
+    /// A leg of an event affecting more clients, e.g. one side of a transfer. The result of the
+    /// leg is replied, and an applied leg is pending until the decision of the pool arrives. The
+    /// counterparty is the client of the other leg.
+    Leg {
+        event: Event,
+        counterparty: u16,
+        reply: mpsc::Sender<Result<Amount, Reason>>,
+    },
+    /// Decision of the pool about the pending leg of the client, commit or roll back
+    Decision { client: u16, commit: bool },
+    /// Query of the current balances of the given client, or of all the clients of the worker.
+    /// The accounts are replied after all the previously queued messages are handled.
+    Query {
+        client: Option<u16>,
+        reply: mpsc::Sender<Vec<Account>>,
+    },
+    /// Export of the full state of all the clients of the worker, or the ids of the failed ones.
+    /// The state is replied after all the previously queued messages are handled.
+    Export {
+        reply: mpsc::Sender<Result<Vec<ClientState>, Vec<u16>>>,
+    },
+    /// Query of the ledger of all the clients of the worker, or the ids of the failed ones. The
+    /// ledger is replied after all the previously queued messages are handled.
+    Ledger {
+        reply: mpsc::Sender<Result<Vec<LedgerEntry>, Vec<u16>>>,
+    },
+    /// Restores a client from an exported state, replacing the current one
+    Restore(Box<ClientState>),
+    /// No more messages are sent, the worker is stopped after the already queued ones
+    Finish,
+}
+
+/// Clients handled by a worker, a failed client is kept without its state
+pub(crate) type Clients = HashMap<u16, Option<Client>>;
+
+/// Sending side of the queue of a running worker, and the handle to join it
+pub(crate) enum Handle {
+    /// Worker running on an OS thread
+    Thread(mpsc::SyncSender<Message>, std::thread::JoinHandle<Clients>),
+    /// Worker running as a tokio task
+    #[cfg(feature = "async")]
+    Task(
+        tokio::sync::mpsc::Sender<Message>,
+        tokio::task::JoinHandle<Clients>,
+    ),
+}
+
+impl Handle {
+    /// Sends a message to the worker. If its queue is full, it blocks until the worker catches
+    /// up. Blocking on the queue of a task is allowed only outside of the async context.
+    ///
+    /// Returns the time spent blocked.
+    ///
+    /// # Error
+    /// If the worker is not running anymore
+    pub(crate) fn send(&self, message: Message) -> Result<Duration, ()> {
+        let start = Instant::now();
+        match self {
+            Handle::Thread(queue, _) => match queue.try_send(message) {
+                Ok(()) => return Ok(Duration::ZERO),
+                Err(mpsc::TrySendError::Full(message)) => queue.send(message).map_err(drop)?,
+                Err(mpsc::TrySendError::Disconnected(_)) => return Err(()),
+            },
+            #[cfg(feature = "async")]
+            Handle::Task(queue, _) => match queue.try_send(message) {
+                Ok(()) => return Ok(Duration::ZERO),
+                Err(tokio::sync::mpsc::error::TrySendError::Full(message)) => {
+                    queue.blocking_send(message).map_err(drop)?
+                }
+                Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => return Err(()),
+            },
+        }
+
+        Ok(start.elapsed())
+    }
+
+    /// Whether the worker could be joined from the current context by `Handle::join`. A task is
+    /// waited for by blocking the current runtime thread, which is possible only on a
+    /// multi-threaded tokio runtime.
+    ///
+    /// # Error
+    /// If the worker is a task, and the current context is not a multi-threaded tokio runtime
+    pub(crate) fn joinable(&self) -> crate::Result<()> {
+        match self {
+            Handle::Thread(..) => Ok(()),
+            #[cfg(feature = "async")]
+            Handle::Task(..) => match tokio::runtime::Handle::try_current() {
+                Ok(runtime)
+                    if runtime.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread =>
+                {
+                    Ok(())
+                }
+                _ => Err(crate::KrctError::Runtime),
+            },
+        }
+    }
+
+    /// Stops the worker, and waits for it to finish the already queued messages. A task has to be
+    /// `Handle::joinable` first.
+    ///
+    /// Returns the clients of the worker.
+    ///
+    /// # Error
+    /// If the worker itself died, so all of its clients are lost
+    pub(crate) fn join(self) -> Result<Clients, ()> {
+        match self {
+            Handle::Thread(queue, worker) => {
+                // A dead worker cannot receive the finish event, but joining it reports the failure
+                let _ = queue.send(Message::Finish);
+                worker.join().map_err(drop)
+            }
+            #[cfg(feature = "async")]
+            Handle::Task(queue, worker) => {
+                drop(queue);
+                tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(worker))
+                    .map_err(drop)
+            }
+        }
+    }
+
+    /// Stops the worker, and waits for it to finish the already queued messages without blocking
+    /// the async context
+    ///
+    /// Returns the clients of the worker.
+    ///
+    /// # Error
+    /// If the worker itself died, so all of its clients are lost
+    #[cfg(feature = "async")]
+    pub(crate) async fn join_async(self) -> Result<Clients, ()> {
+        match self {
+            Handle::Thread(..) => tokio::task::spawn_blocking(move || self.join())
+                .await
+                .map_err(drop)?,
+            Handle::Task(queue, worker) => {
+                drop(queue);
+                worker.await.map_err(drop)
+            }
+        }
+    }
+}
+
+/// A worker handles the events of a shard of the clients. All the messages of a client are sent
+/// to the same worker, so they are handled in order.
+pub(crate) struct Worker {
+    clients: Clients,
+    rejections: Option<mpsc::Sender<Rejection>>,
+    policy: Policy,
+    ledger: bool,
+}
+
+impl Worker {
+    /// Constructing a new worker. The clients are initialized with the optional rejection
+    /// channel, the given policy and whether their ledger is recorded.
+    pub(crate) fn new(
+        rejections: Option<mpsc::Sender<Rejection>>,
+        policy: Policy,
+        ledger: bool,
+    ) -> Self {
+        Self {
+            clients: HashMap::new(),
+            rejections,
+            policy,
+            ledger,
+        }
+    }
+
+    /// Message handling thread, receiving messages from the sender via the given channel.
+    ///
+    /// # Finish
+    /// Special message to indicate the processing of the events should be finished and the
+    /// handling thread has to be stopped. The clients of the worker are returned.
+    pub(crate) fn start_handling(mut self, channel: mpsc::Receiver<Message>) -> Clients {
+        while let Ok(message) = channel.recv() {
+            if !self.receive(message) {
+                break;
+            }
+        }
+
+        self.clients
+    }
+
+    /// Message handling task, receiving messages until the sender is dropped or the finish event
+    /// arrives. The clients of the worker are returned.
+    #[cfg(feature = "async")]
+    pub(crate) async fn start_handling_async(
+        mut self,
+        mut channel: tokio::sync::mpsc::Receiver<Message>,
+    ) -> Clients {
+        while let Some(message) = channel.recv().await {
+            if !self.receive(message) {
+                break;
+            }
+        }
+
+        self.clients
+    }
+
+    /// Dispatches a message to the addressed client. A client is initialized, when its first
+    /// message arrives. A leg addressed to a failed client is rejected, so the pool does not wait
+    /// for it.
+    ///
+    /// Returns `false`, if the worker has to be stopped.
+    fn receive(&mut self, message: Message) -> bool {
+        match message {
+            Message::Finish => return false,
+            Message::Event(event) => {
+                self.guard(event.client(), |client| client.handle_event(&event));
+            }
+            Message::Leg {
+                event,
+                counterparty,
+                reply,
+            } => {
+                let leg = reply.clone();
+                let handled = self.guard(event.client(), |client| {
+                    client.handle_leg(&event, counterparty, leg)
+                });
+                if !handled {
+                    // The pool is gone only if the processing is aborted
+                    let _ = reply.send(Err(Reason::InvalidState));
+                }
+            }
+            Message::Decision { client, commit } => {
+                if let Some(Some(_)) = self.clients.get(&client) {
+                    self.guard(client, |client| client.decide(commit));
+                }
+            }
+            Message::Query { client, reply } => {
+                let accounts = match client {
+                    Some(client) => self.clients.get(&client).into_iter().collect(),
+                    None => self.clients.values().collect::<Vec<_>>(),
+                }
+                .into_iter()
+                .flatten()
+                .map(Account::from)
+                .collect();
+                // The caller is gone only if it is not interested in the result anymore
+                let _ = reply.send(accounts);
+            }
+            Message::Export { reply } => {
+                let mut failed = Vec::new();
+                let mut clients = Vec::new();
+                for (&id, client) in &self.clients {
+                    match client {
+                        Some(client) => clients.push(ClientState::from(client)),
+                        None => failed.push(id),
+                    }
+                }
+                // The caller is gone only if it is not interested in the result anymore
+                let _ = reply.send(if failed.is_empty() {
+                    Ok(clients)
+                } else {
+                    Err(failed)
+                });
+            }
+            Message::Ledger { reply } => {
+                let mut failed = Vec::new();
+                let mut entries = Vec::new();
+                for (&id, client) in &self.clients {
+                    match client {
+                        Some(client) => entries.extend(client.ledger()),
+                        None => failed.push(id),
+                    }
+                }
+                // The caller is gone only if it is not interested in the result anymore
+                let _ = reply.send(if failed.is_empty() {
+                    Ok(entries)
+                } else {
+                    Err(failed)
+                });
+            }
+            Message::Restore(state) => {
+                let client =
+                    state.into_client(self.rejections.clone(), self.policy.clone(), self.ledger);
+                self.clients.insert(client.id, Some(client));
+            }
+        }
+
+        true
+    }
+
+    /// Runs the given handler on a client, the client is initialized, if a previously not known
+    /// Client ID arrives. If the handler panics, then the client is marked as failed and its
+    /// further messages are ignored, but the other clients of the worker are still handled.
+    ///
+    /// Returns `false`, if the client has failed.
+    fn guard<F>(&mut self, client_id: u16, handler: F) -> bool
+    where
+        F: FnOnce(&mut Client),
+    {
+        let (rejections, policy, ledger) = (&self.rejections, &self.policy, self.ledger);
+        let slot = self.clients.entry(client_id).or_insert_with(|| {
+            Some(Client::new(
+                client_id,
+                rejections.clone(),
+                policy.clone(),
+                ledger,
+            ))
+        });
+        let client = match slot {
+            Some(client) => client,
+            None => return false,
+        };
+        if panic::catch_unwind(AssertUnwindSafe(|| handler(client))).is_err() {
+            *slot = None;
+            return false;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Message, Worker};
+    use crate::pool::{Amount, Event, Reason};
+
+    #[test]
+    fn test_failed_client_is_isolated() {
+        let mut worker = Worker::new(None, Default::default(), false);
+        assert!(worker.guard(1, |_| ()));
+        assert!(!worker.guard(2, |_| panic!("Client failure")));
+        assert!(!worker.guard(2, |_| ()));
+
+        let (reply, result) = std::sync::mpsc::channel();
+        assert!(worker.receive(Message::Leg {
+            event: Event::Deposit {
+                client: 2,
+                tx: 1,
+                amount: Amount::from(1.0),
+            },
+            counterparty: 1,
+            reply,
+        }));
+        assert_eq!(result.recv(), Ok(Err(Reason::InvalidState)));
+
+        assert!(worker.receive(Message::Event(Event::Deposit {
+            client: 1,
+            tx: 2,
+            amount: Amount::from(1.0),
+        })));
+        assert!(!worker.receive(Message::Finish));
+        assert_eq!(
+            worker
+                .clients
+                .get(&1)
+                .and_then(Option::as_ref)
+                .map(|client| client.total),
+            Some(Amount::from(1.0))
+        );
+        assert!(matches!(worker.clients.get(&2), Some(None)));
+    }
+}
@@ -1,18 +1,29 @@
+use crate::pool::Amount;
+
 /// Type of the event, could be:
 /// * deposit
 /// * withdraw
 /// * dispute
 /// * resolve
 /// * chargeback
-#[derive(Debug, serde::Deserialize)]
+/// * lock
+/// * unlock
+/// * transfer
+/// * fee
+/// * interest
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "lowercase")]
-#[cfg_attr(test, derive(PartialEq))]
-pub(crate) enum TransactionType {
+pub enum TransactionType {
     Deposit,
     Withdrawal,
     Dispute,
     Resolve,
     Chargeback,
+    Lock,
+    Unlock,
+    Transfer,
+    Fee,
+    Interest,
 
     #[serde(other)]
     Unknown,
@@ -20,9 +31,14 @@ pub(crate) enum TransactionType {
 
 /// Each line of teh input CSV file is a well-defined event, described by the `Transaction` struct.
 /// All event has a type, described by the `TransactionType` enum. Each event assigned by a client
-/// id and a transaction id. Optionally the amount, not all transaction have an amount.
-#[derive(Debug, serde::Deserialize)]
-pub(crate) struct Transaction {
+/// id and a transaction id. Optionally the amount, not all transaction have an amount. Only a
+/// transfer has a destination client.
+///
+/// A transaction is serialized with the columns of the input, `type`, `client`, `tx`, `amount` and
+/// `destination`, both to CSV and JSON. The amount is a decimal string even in JSON, so it is
+/// kept exact.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Transaction {
     #[serde(rename = "type")]
     pub(crate) _type: TransactionType,
     #[serde(rename = "client")]
@@ -30,7 +46,53 @@ pub(crate) struct Transaction {
     #[serde(rename = "tx")]
     pub(crate) transaction_id: u32,
     #[serde(default)]
-    pub(crate) amount: Option<f32>,
+    pub(crate) amount: Option<Amount>,
+    #[serde(default)]
+    pub(crate) destination: Option<u16>,
+}
+
+impl Transaction {
+    /// Constructing a new transaction of the given type for the given client
+    pub fn new(kind: TransactionType, client: u16, tx: u32, amount: Option<Amount>) -> Self {
+        Self {
+            _type: kind,
+            client_id: client,
+            transaction_id: tx,
+            amount,
+            destination: None,
+        }
+    }
+
+    /// The transaction with the given destination client, needed only by a transfer
+    pub fn with_destination(mut self, destination: u16) -> Self {
+        self.destination = Some(destination);
+        self
+    }
+
+    /// Type of the transaction
+    pub fn kind(&self) -> TransactionType {
+        self._type
+    }
+
+    /// Client ID of the transaction
+    pub fn client(&self) -> u16 {
+        self.client_id
+    }
+
+    /// Transaction ID, or the referenced transaction of a dispute, resolve or chargeback
+    pub fn tx(&self) -> u32 {
+        self.transaction_id
+    }
+
+    /// Amount of the transaction, if it has any
+    pub fn amount(&self) -> Option<Amount> {
+        self.amount
+    }
+
+    /// Destination client of a transfer
+    pub fn destination(&self) -> Option<u16> {
+        self.destination
+    }
 }
 
 #[cfg(test)]
@@ -50,7 +112,7 @@ mod common {
 #[cfg(test)]
 mod positive_test_cases {
     use super::common::create_test_file;
-    use super::{Transaction, TransactionType};
+    use super::{Amount, Transaction, TransactionType};
     use crate::Krct;
 
     #[test]
@@ -72,7 +134,7 @@ mod positive_test_cases {
         assert_eq!(record._type, TransactionType::Deposit);
         assert_eq!(record.client_id, 1);
         assert_eq!(record.transaction_id, 1);
-        assert_eq!(record.amount, Some(1.0));
+        assert_eq!(record.amount, Some(Amount::from(1.0)));
     }
 
     #[test]
@@ -94,7 +156,7 @@ mod positive_test_cases {
         assert_eq!(record._type, TransactionType::Withdrawal);
         assert_eq!(record.client_id, 1);
         assert_eq!(record.transaction_id, 1);
-        assert_eq!(record.amount, Some(1.0));
+        assert_eq!(record.amount, Some(Amount::from(1.0)));
     }
 
     #[test]
@@ -163,6 +225,36 @@ mod positive_test_cases {
         assert_eq!(record.amount, None);
     }
 
+    #[test]
+    fn test_transfer_parsing() {
+        let test_case = create_test_file(
+            "\
+        type,client,tx,amount,destination\n\
+        deposit,1,1,1.0\n\
+        transfer,1,2,1.0,2\
+        ",
+        );
+        let mut reader = Krct::get_reader(test_case);
+        let mut tx = reader.deserialize::<Transaction>();
+
+        let record = tx.next();
+        assert!(record.is_some());
+        let record = record.unwrap();
+        assert!(record.is_ok(), "{}", record.unwrap_err());
+        assert_eq!(record.unwrap().destination, None);
+
+        let record = tx.next();
+        assert!(record.is_some());
+        let record = record.unwrap();
+        assert!(record.is_ok(), "{}", record.unwrap_err());
+        let record = record.unwrap();
+        assert_eq!(record._type, TransactionType::Transfer);
+        assert_eq!(record.client_id, 1);
+        assert_eq!(record.transaction_id, 2);
+        assert_eq!(record.amount, Some(Amount::from(1.0)));
+        assert_eq!(record.destination, Some(2));
+    }
+
     #[test]
     fn test_parsing_with_whitespaces() {
         let test_case = create_test_file(
@@ -182,7 +274,26 @@ mod positive_test_cases {
         assert_eq!(record._type, TransactionType::Deposit);
         assert_eq!(record.client_id, 1);
         assert_eq!(record.transaction_id, 1);
-        assert_eq!(record.amount, Some(1.0));
+        assert_eq!(record.amount, Some(Amount::from(1.0)));
+    }
+
+    #[test]
+    fn test_amount_parsed_exactly() {
+        let test_case = create_test_file(
+            "\
+        type,client,tx,amount\n\
+        deposit,1,1,0.1234\
+        ",
+        );
+        let mut reader = Krct::get_reader(test_case);
+        let mut tx = reader.deserialize::<Transaction>();
+
+        let record = tx.next();
+        assert!(record.is_some());
+        let record = record.unwrap();
+        assert!(record.is_ok(), "{}", record.unwrap_err());
+        let record = record.unwrap();
+        assert_eq!(record.amount, Some("0.1234".parse().unwrap()));
     }
 }
 
@@ -295,4 +406,85 @@ mod negative_test_cases {
         let record = record.unwrap();
         assert!(record.is_err());
     }
+
+    #[test]
+    fn test_amount_with_too_many_fractional_digits() {
+        let test_case = create_test_file(
+            "\
+        type,client,tx,amount\n\
+        deposit,1,1,1.00001\
+        ",
+        );
+        let mut reader = Krct::get_reader(test_case);
+        let mut tx = reader.deserialize::<Transaction>();
+
+        let record = tx.next();
+        assert!(record.is_some());
+        let record = record.unwrap();
+        assert!(record.is_err());
+    }
+}
+
+#[cfg(test)]
+mod serde_test_cases {
+    use super::{Amount, Transaction, TransactionType};
+    use crate::Event;
+
+    fn transfer() -> Transaction {
+        Transaction::new(
+            TransactionType::Transfer,
+            1,
+            2,
+            Some("1.5".parse::<Amount>().unwrap()),
+        )
+        .with_destination(3)
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        assert!(writer.serialize(transfer()).is_ok());
+        assert!(writer
+            .serialize(Transaction::new(TransactionType::Dispute, 1, 2, None))
+            .is_ok());
+        let buffer = writer.into_inner().expect("Failed to write CSV");
+        assert_eq!(
+            String::from_utf8_lossy(&buffer),
+            "type,client,tx,amount,destination\n\
+            transfer,1,2,1.5,3\n\
+            dispute,1,2,,\n"
+        );
+
+        let mut reader = csv::Reader::from_reader(buffer.as_slice());
+        let transactions = reader
+            .deserialize::<Transaction>()
+            .collect::<Result<Vec<_>, _>>()
+            .expect("Failed to read CSV");
+        assert_eq!(
+            transactions,
+            vec![
+                transfer(),
+                Transaction::new(TransactionType::Dispute, 1, 2, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let json = serde_json::to_string(&transfer()).expect("Failed to write JSON");
+        assert_eq!(
+            json,
+            "{\"type\":\"transfer\",\"client\":1,\"tx\":2,\"amount\":\"1.5\",\"destination\":3}"
+        );
+        let transaction = serde_json::from_str::<Transaction>(
+            "{\"type\":\"withdrawal\",\"client\":1,\"tx\":3,\"amount\":\"0.5\"}",
+        )
+        .expect("Failed to read JSON");
+        assert_eq!(transaction.kind(), TransactionType::Withdrawal);
+        assert_eq!(transaction.destination(), None);
+        assert_eq!(
+            Event::try_from(transaction).ok(),
+            Some(Event::withdrawal(1, 3, "0.5".parse().unwrap()))
+        );
+    }
 }
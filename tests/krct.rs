@@ -1,6 +1,10 @@
 mod steps;
 
 use cucumber::WorldInit;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Run the scenarios against the async engine
+static ASYNCHRONOUS: AtomicBool = AtomicBool::new(false);
 
 #[derive(Debug, Default)]
 struct Output {
@@ -24,6 +28,16 @@ impl std::io::Write for Output {
 struct KrctWorld {
     tempfile: tempfile::NamedTempFile,
     output: Output,
+    report: Output,
+    ledger: Output,
+    rejected_rows: Vec<String>,
+    error: Option<krct::KrctError>,
+    asynchronous: bool,
+    stderr: String,
+    status: Option<i32>,
+    state: tempfile::TempPath,
+    journal: tempfile::TempDir,
+    directory: tempfile::TempDir,
 }
 
 #[async_trait::async_trait(?Send)]
@@ -35,6 +49,18 @@ impl cucumber::World for KrctWorld {
             tempfile: tempfile::NamedTempFile::new()
                 .expect("Failed to initialize test environment"),
             output: Output::default(),
+            report: Output::default(),
+            ledger: Output::default(),
+            rejected_rows: Vec::new(),
+            error: None,
+            asynchronous: ASYNCHRONOUS.load(Ordering::Relaxed),
+            stderr: String::new(),
+            status: None,
+            state: tempfile::NamedTempFile::new()
+                .expect("Failed to initialize test environment")
+                .into_temp_path(),
+            journal: tempfile::tempdir().expect("Failed to initialize test environment"),
+            directory: tempfile::tempdir().expect("Failed to initialize test environment"),
         })
     }
 }
@@ -42,4 +68,10 @@ impl cucumber::World for KrctWorld {
 #[tokio::main]
 async fn main() {
     KrctWorld::run("features/").await;
+
+    #[cfg(feature = "async")]
+    {
+        ASYNCHRONOUS.store(true, Ordering::Relaxed);
+        KrctWorld::run("features/").await;
+    }
 }
@@ -1,11 +1,35 @@
 use crate::KrctWorld;
 use cucumber::{gherkin::Step, given};
-use std::io::Write;
+use std::io::{Seek, Write};
 
 #[given("the following CSV file")]
 async fn write_content(w: &mut KrctWorld, step: &Step) {
     w.tempfile
         .as_file()
-        .write_all(step.docstring().cloned().unwrap_or_default().as_bytes())
+        .write_all(
+            step.docstring()
+                .map(|content| content.trim_start_matches('\n'))
+                .unwrap_or_default()
+                .as_bytes(),
+        )
         .expect("Failed to write test file")
 }
+
+#[given("the following CSV file replacing the previous one")]
+async fn replace_content(w: &mut KrctWorld, step: &Step) {
+    let mut file = w.tempfile.as_file();
+    file.set_len(0).expect("Failed to truncate test file");
+    file.rewind().expect("Failed to rewind test file");
+    write_content(w, step).await;
+}
+
+#[given(regex = r#"^the following CSV file "(.*)" in the input directory$"#)]
+async fn write_directory_content(w: &mut KrctWorld, step: &Step, name: String) {
+    std::fs::write(
+        w.directory.path().join(name),
+        step.docstring()
+            .map(|content| content.trim_start_matches('\n'))
+            .unwrap_or_default(),
+    )
+    .expect("Failed to write test file")
+}
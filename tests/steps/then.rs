@@ -8,3 +8,57 @@ async fn assert_content(w: &mut KrctWorld, step: &Step) {
         step.docstring().cloned().unwrap_or_default().trim()
     )
 }
+
+#[then("the following rejections should be reported")]
+async fn assert_report(w: &mut KrctWorld, step: &Step) {
+    pretty_assertions::assert_eq!(
+        w.report.buffer.trim(),
+        step.docstring().cloned().unwrap_or_default().trim()
+    )
+}
+
+#[then("the following ledger should be written")]
+async fn assert_ledger(w: &mut KrctWorld, step: &Step) {
+    pretty_assertions::assert_eq!(
+        w.ledger.buffer.trim(),
+        step.docstring().cloned().unwrap_or_default().trim()
+    )
+}
+
+#[then("the following should be written to the state file")]
+async fn assert_state_file(w: &mut KrctWorld, step: &Step) {
+    let content = std::fs::read_to_string(&w.state).expect("Failed to read state file");
+    pretty_assertions::assert_eq!(
+        content.trim(),
+        step.docstring().cloned().unwrap_or_default().trim()
+    )
+}
+
+#[then("the following rows should be rejected")]
+async fn assert_rejected_rows(w: &mut KrctWorld, step: &Step) {
+    pretty_assertions::assert_eq!(
+        w.rejected_rows.join("\n"),
+        step.docstring().cloned().unwrap_or_default().trim()
+    )
+}
+
+#[then(regex = r"^the engine should fail at line (\d+)$")]
+async fn assert_failed_at_line(w: &mut KrctWorld, line: u64) {
+    match &w.error {
+        Some(krct::KrctError::MalformedRow(row)) => assert_eq!(row.line, line),
+        other => panic!("Expected a malformed row error, got: {:?}", other),
+    }
+}
+
+#[then(regex = r"^the exit code should be (\d+)$")]
+async fn assert_exit_code(w: &mut KrctWorld, code: i32) {
+    assert_eq!(w.status, Some(code), "stderr: {}", w.stderr);
+}
+
+#[then("the following error should be printed")]
+async fn assert_error(w: &mut KrctWorld, step: &Step) {
+    pretty_assertions::assert_eq!(
+        w.stderr.trim(),
+        step.docstring().cloned().unwrap_or_default().trim()
+    )
+}
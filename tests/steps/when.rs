@@ -1,10 +1,222 @@
 use crate::KrctWorld;
 use cucumber::when;
 
+/// Runs the engine with the given configuration on the test file, either the sync or the async
+/// engine according to the world. The collected rejections and the ledger are reported in the
+/// given format.
+async fn execute(w: &mut KrctWorld, config: krct::Config, format: krct::ReportFormat) {
+    let ledger = config.ledger;
+    let result = if w.asynchronous {
+        read_async(w, config).await
+    } else {
+        let input = std::fs::File::open(w.tempfile.path()).expect("Failed to open test file");
+        krct::Krct::read_with(input, config)
+    };
+    let mut krct = match result {
+        Ok(krct) => krct,
+        Err(err) => {
+            w.error = Some(err);
+            return;
+        }
+    };
+
+    w.rejected_rows = krct
+        .rejected_rows()
+        .iter()
+        .map(|row| {
+            let fields = row.record.iter().collect::<Vec<_>>().join(",");
+            format!("{}: {}", row.line, fields)
+        })
+        .collect();
+    let rejections = krct.rejections();
+    if ledger {
+        let ledger = if w.asynchronous {
+            ledger_async(&krct).await
+        } else {
+            krct.ledger()
+        };
+        ledger
+            .and_then(|ledger| ledger.dump(&mut w.ledger, format))
+            .expect("Failed to write ledger");
+    }
+    if w.asynchronous {
+        dump_async(w, krct).await;
+    } else {
+        krct.dump_sorted(&mut w.output)
+            .expect("Failed to write output");
+    }
+    if let Some(rejections) = rejections {
+        rejections
+            .dump(&mut w.report, format)
+            .expect("Failed to write rejection report");
+    }
+}
+
+#[cfg(feature = "async")]
+async fn read_async(w: &KrctWorld, config: krct::Config) -> krct::Result<krct::Krct> {
+    let input = tokio::fs::File::open(w.tempfile.path())
+        .await
+        .expect("Failed to open test file");
+    krct::Krct::read_async(input, config).await
+}
+
+#[cfg(feature = "async")]
+async fn dump_async(w: &mut KrctWorld, krct: krct::Krct) {
+    let mut output = Vec::new();
+    krct.dump_sorted_async(&mut output)
+        .await
+        .expect("Failed to write output");
+    w.output.buffer = String::from_utf8(output).expect("Output should be UTF-8");
+}
+
+#[cfg(feature = "async")]
+async fn ledger_async(krct: &krct::Krct) -> krct::Result<krct::Ledger> {
+    krct.ledger_async().await
+}
+
+#[cfg(not(feature = "async"))]
+async fn read_async(_: &KrctWorld, _: krct::Config) -> krct::Result<krct::Krct> {
+    unreachable!("The async engine requires the async feature")
+}
+
+#[cfg(not(feature = "async"))]
+async fn dump_async(_: &mut KrctWorld, _: krct::Krct) {
+    unreachable!("The async engine requires the async feature")
+}
+
+#[cfg(not(feature = "async"))]
+async fn ledger_async(_: &krct::Krct) -> krct::Result<krct::Ledger> {
+    unreachable!("The async engine requires the async feature")
+}
+
 #[when("the engine is executed")]
 async fn write_content(w: &mut KrctWorld) {
-    krct::Krct::try_from(w.tempfile.path())
-        .expect("Error occurred running the engine!")
+    execute(w, krct::Config::default(), krct::ReportFormat::Csv).await;
+}
+
+#[when(regex = r"^the engine is executed in (lenient|abort|collect) mode$")]
+async fn execute_with_strictness(w: &mut KrctWorld, strictness: String) {
+    let config = krct::Config {
+        strictness: strictness.parse().expect("Unknown strictness"),
+        ..krct::Config::default()
+    };
+    execute(w, config, krct::ReportFormat::Csv).await;
+}
+
+#[when(regex = r"^the engine is executed with (csv|ndjson) rejection report$")]
+async fn execute_with_rejections(w: &mut KrctWorld, format: String) {
+    let config = krct::Config {
+        rejections: true,
+        ..krct::Config::default()
+    };
+    execute(w, config, format.parse().expect("Unknown report format")).await;
+}
+
+#[when(regex = r"^the engine is executed with (csv|ndjson) ledger$")]
+async fn execute_with_ledger(w: &mut KrctWorld, format: String) {
+    let config = krct::Config {
+        ledger: true,
+        ..krct::Config::default()
+    };
+    execute(w, config, format.parse().expect("Unknown report format")).await;
+}
+
+#[when(regex = r"^the engine is executed with (forbid|allow|cap) negative balance policy$")]
+async fn execute_with_negative_balance(w: &mut KrctWorld, policy: String) {
+    let config = krct::Config {
+        rejections: true,
+        policy: krct::Policy {
+            negative_balance: policy.parse().expect("Unknown negative balance policy"),
+            ..krct::Policy::default()
+        },
+        ..krct::Config::default()
+    };
+    execute(w, config, krct::ReportFormat::Csv).await;
+}
+
+#[when(regex = r"^the engine is executed with locked accounts accepting (.*)$")]
+async fn execute_with_locked_policy(w: &mut KrctWorld, accepted: String) {
+    let config = krct::Config {
+        rejections: true,
+        policy: krct::Policy {
+            locked: accepted.parse().expect("Unknown locked policy"),
+            ..krct::Policy::default()
+        },
+        ..krct::Config::default()
+    };
+    execute(w, config, krct::ReportFormat::Csv).await;
+}
+
+#[when(regex = r"^the engine is executed with (forbid|allow|cap) fee policy$")]
+async fn execute_with_fee_policy(w: &mut KrctWorld, policy: String) {
+    let config = krct::Config {
+        rejections: true,
+        policy: krct::Policy {
+            fee: policy.parse().expect("Unknown fee policy"),
+            ..krct::Policy::default()
+        },
+        ..krct::Config::default()
+    };
+    execute(w, config, krct::ReportFormat::Csv).await;
+}
+
+#[when("the engine is fed record by record")]
+async fn feed_records(w: &mut KrctWorld) {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(w.tempfile.path())
+        .expect("Failed to open test file");
+    let mut engine = krct::Engine::new(krct::Config::default());
+    for record in reader.records() {
+        engine
+            .submit_record(&record.expect("Failed to read record"))
+            .expect("Failed to submit record");
+    }
+    engine
         .dump_sorted(&mut w.output)
         .expect("Failed to write output");
 }
+
+#[when(regex = r"^the engine is executed with (csv|json|ndjson|table) output$")]
+async fn execute_with_output_format(w: &mut KrctWorld, format: String) {
+    let input = std::fs::File::open(w.tempfile.path()).expect("Failed to open test file");
+    krct::Krct::read(input)
+        .expect("Failed to read test file")
+        .dump_sorted_as(
+            &mut w.output,
+            format.parse().expect("Unknown output format"),
+        )
+        .expect("Failed to write output");
+}
+
+#[when(regex = r#"^the binary is executed with "(.*)"$"#)]
+async fn execute_binary(w: &mut KrctWorld, args: String) {
+    run_binary(w, args, std::process::Stdio::null());
+}
+
+#[when(regex = r#"^the binary is executed with "(.*)" reading the CSV file from stdin$"#)]
+async fn execute_binary_with_stdin(w: &mut KrctWorld, args: String) {
+    let stdin = w.tempfile.reopen().expect("Failed to open test file");
+    run_binary(w, args, stdin.into());
+}
+
+fn run_binary(w: &mut KrctWorld, args: String, stdin: std::process::Stdio) {
+    let input = w.tempfile.path().as_os_str().to_owned();
+    let state = w.state.as_os_str().to_owned();
+    let journal = w.journal.path().as_os_str().to_owned();
+    let directory = w.directory.path().as_os_str().to_owned();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_krct"))
+        .args(args.split_whitespace().map(|arg| match arg {
+            "{input}" => input.clone(),
+            "{state}" => state.clone(),
+            "{journal}" => journal.clone(),
+            "{directory}" => directory.clone(),
+            arg => arg.into(),
+        }))
+        .stdin(stdin)
+        .output()
+        .expect("Failed to execute binary");
+    w.output.buffer = String::from_utf8_lossy(&output.stdout).into_owned();
+    w.stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    w.status = output.status.code();
+}